@@ -0,0 +1,521 @@
+use crate::bias::{weighted_mean, Biases};
+use crate::bindings::MfParameter;
+use crate::divergence::Monitor;
+use crate::sgd::{self, dot, Rng, SEED};
+use crate::{Error, Loss, Matrix, Model, Params};
+use alloc::vec;
+use alloc::vec::Vec;
+
+// warm-started from the previous factors, so a few steps per iteration are enough
+const CG_STEPS: usize = 3;
+
+/// The method used to fit a model.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Solver {
+    /// Stochastic gradient descent (LIBMF).
+    Sgd,
+    /// Stochastic gradient descent in Rust (for real-valued and binary losses).
+    ///
+    /// Uses the same updates as LIBMF and also supports weights.
+    RustSgd,
+    /// Alternating least squares with conjugate gradient (for `Loss::RealL2` and `Loss::OneClassL2`).
+    ///
+    /// Trained in Rust. For `Loss::OneClassL2`, entries not in the training set have
+    /// a value of `c` and a weight of `alpha`, like weighted ALS for implicit feedback.
+    Als,
+}
+
+// the entries for each row (or column) with the index on the other side
+struct Index {
+    starts: Vec<usize>,
+    entries: Vec<(usize, f32, f32)>,
+}
+
+impl Index {
+    fn new(data: &Matrix, len: usize, transpose: bool) -> Self {
+        let key = |i: usize| {
+            let x = &data.data[i];
+            if transpose {
+                (x.v as usize, x.u as usize)
+            } else {
+                (x.u as usize, x.v as usize)
+            }
+        };
+
+        let mut starts = vec![0; len + 1];
+        for i in 0..data.len() {
+            starts[key(i).0 + 1] += 1;
+        }
+        for i in 0..len {
+            starts[i + 1] += starts[i];
+        }
+        let mut next = starts.clone();
+        let mut entries = vec![(0, 0.0, 0.0); data.len()];
+        for i in 0..data.len() {
+            let (a, b) = key(i);
+            entries[next[a]] = (b, data.data[i].r, data.weight(i));
+            next[a] += 1;
+        }
+        Self { starts, entries }
+    }
+
+    fn get(&self, index: usize) -> &[(usize, f32, f32)] {
+        &self.entries[self.starts[index]..self.starts[index + 1]]
+    }
+}
+
+// the parts of the least squares problem shared by every row (or column)
+struct Problem<'a> {
+    k: usize,
+    y: &'a [f32],
+    lambda: f32,
+    nmf: bool,
+    // subtracted from the values (empty when disabled)
+    bias: &'a [f32],
+    other_bias: &'a [f32],
+    // for entries not in the training set (empty for explicit feedback)
+    implicit: Option<Implicit>,
+}
+
+struct Implicit {
+    alpha: f32,
+    c: f32,
+    // YᵀY and the sum of the rows of Y
+    gram: Vec<f32>,
+    sum: Vec<f32>,
+}
+
+impl Implicit {
+    fn new(y: &[f32], k: usize, alpha: f32, c: f32) -> Self {
+        let mut gram = vec![0.0; k * k];
+        let mut sum = vec![0.0; k];
+        for v in y.chunks(k) {
+            for a in 0..k {
+                sum[a] += v[a];
+                for b in 0..k {
+                    gram[a * k + b] += v[a] * v[b];
+                }
+            }
+        }
+        Self {
+            alpha,
+            c,
+            gram,
+            sum,
+        }
+    }
+}
+
+impl Problem<'_> {
+    fn factors(&self, index: usize) -> &[f32] {
+        &self.y[index * self.k..(index + 1) * self.k]
+    }
+
+    // A x for the normal equations of one row
+    fn multiply(&self, entries: &[(usize, f32, f32)], lambda: f32, x: &[f32], out: &mut [f32]) {
+        let k = self.k;
+        let alpha = match &self.implicit {
+            Some(implicit) => {
+                for (a, o) in out.iter_mut().enumerate() {
+                    *o = implicit.alpha * dot(&implicit.gram[a * k..(a + 1) * k], x);
+                }
+                implicit.alpha
+            }
+            None => {
+                out.fill(0.0);
+                0.0
+            }
+        };
+        // the weight of entries in the training set replaces alpha
+        for &(j, _, w) in entries {
+            let y = self.factors(j);
+            let s = (w - alpha) * dot(y, x);
+            for (o, y) in out.iter_mut().zip(y) {
+                *o += s * y;
+            }
+        }
+        for (o, x) in out.iter_mut().zip(x) {
+            *o += lambda * x;
+        }
+    }
+
+    fn solve(&self, index: usize, entries: &[(usize, f32, f32)], x: &mut [f32]) {
+        let k = self.k;
+        let mut b = vec![0.0; k];
+        let mut weight = 0.0;
+        let bias = self.bias.get(index).copied().unwrap_or(0.0);
+        for &(j, r, w) in entries {
+            let y = self.factors(j);
+            let r = r - bias - self.other_bias.get(j).copied().unwrap_or(0.0);
+            for (b, y) in b.iter_mut().zip(y) {
+                *b += w * r * y;
+            }
+            weight += w;
+        }
+        let lambda = match &self.implicit {
+            Some(implicit) => {
+                // alpha c for every entry not in the training set
+                let mut rest = implicit.sum.clone();
+                for &(j, _, _) in entries {
+                    for (s, y) in rest.iter_mut().zip(self.factors(j)) {
+                        *s -= y;
+                    }
+                }
+                for (b, s) in b.iter_mut().zip(rest) {
+                    *b += implicit.alpha * implicit.c * s;
+                }
+                self.lambda
+            }
+            // scaled by the weight of the entries like the per-entry updates of SGD
+            None => self.lambda * weight,
+        };
+
+        // conjugate gradient
+        let mut ax = vec![0.0; k];
+        self.multiply(entries, lambda, x, &mut ax);
+        let mut r: Vec<f32> = b.iter().zip(&ax).map(|(b, a)| b - a).collect();
+        let mut p = r.clone();
+        let mut rs_old = dot(&r, &r);
+        let mut ap = ax;
+        for _ in 0..CG_STEPS {
+            if rs_old < 1e-10 {
+                break;
+            }
+            self.multiply(entries, lambda, &p, &mut ap);
+            let step = rs_old / dot(&p, &ap);
+            for d in 0..k {
+                x[d] += step * p[d];
+                r[d] -= step * ap[d];
+            }
+            let rs_new = dot(&r, &r);
+            for d in 0..k {
+                p[d] = r[d] + rs_new / rs_old * p[d];
+            }
+            rs_old = rs_new;
+        }
+
+        if self.nmf {
+            x.iter_mut().for_each(|v| *v = v.max(0.0));
+        }
+    }
+}
+
+// weighted alternating least squares, where each step solves the least squares
+// problem for every row (then every column, then the biases) with the rest fixed
+//
+// `param` can differ from `params` when retrying
+pub(crate) fn train(
+    data: &Matrix,
+    eval_set: Option<&Matrix>,
+    param: &MfParameter,
+    params: &Params,
+) -> Result<Model, Error> {
+    let k = param.k as usize;
+    let mut m = data.rows() as usize;
+    let mut n = data.columns() as usize;
+    // LIBMF uses the larger dimensions
+    if let Some(eval_set) = eval_set {
+        m = m.max(eval_set.rows() as usize);
+        n = n.max(eval_set.columns() as usize);
+    }
+
+    let rows = Index::new(data, m, false);
+    let columns = Index::new(data, n, true);
+
+    // rows and columns without entries stay at zero so they do not affect the others
+    let mut rng = Rng(SEED);
+    let scale = libm::sqrtf(1.0 / k as f32);
+    let mut init = |index: &Index, len: usize| -> Vec<f32> {
+        let mut factors = vec![0.0; len * k];
+        for (i, v) in factors.chunks_mut(k).enumerate() {
+            if !index.get(i).is_empty() {
+                v.iter_mut().for_each(|v| *v = rng.next_f32() * scale);
+            }
+        }
+        factors
+    };
+    let mut p = init(&rows, m);
+    let mut q = init(&columns, n);
+    let mut biases = Biases::new(params.get_row_bias(), params.get_column_bias(), m, n);
+
+    #[cfg(feature = "std")]
+    let progress = sgd::Progress::new(data, eval_set, param);
+    let mut monitor = Monitor::new(params.get_divergence_check());
+    // the loss is only needed for progress and the divergence check
+    let needs_loss = (cfg!(feature = "std") && !param.quiet) || monitor.enabled();
+
+    for iter in 0..param.nr_iters {
+        update(
+            &mut p,
+            &q,
+            &rows,
+            param.lambda_p2,
+            (&biases.rows, &biases.columns),
+            param,
+        );
+        update(
+            &mut q,
+            &p,
+            &columns,
+            param.lambda_q2,
+            (&biases.columns, &biases.rows),
+            param,
+        );
+        if !biases.is_empty() {
+            biases.solve(
+                data,
+                |x| {
+                    let (u, v) = (x.u as usize, x.v as usize);
+                    dot(&p[u * k..(u + 1) * k], &q[v * k..(v + 1) * k])
+                },
+                params.get_lambda_bias(),
+            );
+        }
+
+        if !needs_loss {
+            continue;
+        }
+        // only for entries in the training set
+        let mut loss = 0.0;
+        for (i, x) in data.data.iter().enumerate() {
+            let (u, v) = (x.u as usize, x.v as usize);
+            let e = x.r
+                - dot(&p[u * k..(u + 1) * k], &q[v * k..(v + 1) * k])
+                - biases.predict(x.u, x.v);
+            loss += data.weight(i) as f64 * (e * e) as f64;
+        }
+        #[cfg(feature = "std")]
+        progress.print(iter, loss, &p, &q, &biases.rows, &biases.columns);
+        monitor.check(iter, loss)?;
+    }
+
+    sgd::fill_unseen(data, k, &mut p, &mut q);
+    let mut model = Model::from_factors(param.fun, param.k, weighted_mean(data), p, q);
+    model.biases = biases;
+    Ok(model)
+}
+
+// the biases are for this side and the other side
+fn update(
+    x: &mut [f32],
+    y: &[f32],
+    index: &Index,
+    lambda: f32,
+    (bias, other_bias): (&[f32], &[f32]),
+    param: &MfParameter,
+) {
+    let k = param.k as usize;
+    let problem = Problem {
+        k,
+        y,
+        lambda,
+        nmf: param.do_nmf,
+        bias,
+        other_bias,
+        implicit: if matches!(param.fun, Loss::OneClassL2) {
+            Some(Implicit::new(y, k, param.alpha, param.c))
+        } else {
+            None
+        },
+    };
+    run(x, index, &problem, param.nr_threads.max(1) as usize);
+}
+
+// rows are independent, so they are split between threads
+#[cfg(feature = "std")]
+fn run(x: &mut [f32], index: &Index, problem: &Problem, threads: usize) {
+    let k = problem.k;
+    let len = x.len() / k;
+    if threads < 2 || len < 2 {
+        return solve_rows(x, 0, index, problem);
+    }
+
+    let chunk_size = (len + threads - 1) / threads;
+    std::thread::scope(|s| {
+        for (i, chunk) in x.chunks_mut(chunk_size * k).enumerate() {
+            s.spawn(move || solve_rows(chunk, i * chunk_size, index, problem));
+        }
+    });
+}
+
+#[cfg(not(feature = "std"))]
+fn run(x: &mut [f32], index: &Index, problem: &Problem, _threads: usize) {
+    solve_rows(x, 0, index, problem)
+}
+
+fn solve_rows(x: &mut [f32], start: usize, index: &Index, problem: &Problem) {
+    for (i, v) in x.chunks_mut(problem.k).enumerate() {
+        let entries = index.get(start + i);
+        if !entries.is_empty() {
+            problem.solve(start + i, entries, v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DivergenceCheck, Loss, Matrix, Model, ParameterError, Solver};
+
+    fn generate_data() -> Matrix {
+        let mut data = Matrix::new();
+        for i in 0..20 {
+            for j in 0..10 {
+                if (i + j) % 3 != 0 {
+                    data.push(i, j, ((i % 4) + (j % 3)) as f32);
+                }
+            }
+        }
+        data
+    }
+
+    // rows and columns in the same group have entries, except for the diagonal
+    fn generate_implicit() -> Matrix {
+        let mut data = Matrix::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                if i % 2 == j % 2 && i != j {
+                    data.push(i, j, 1.0);
+                }
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_als() {
+        let data = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::Als)
+            .lambda_p2(0.01)
+            .lambda_q2(0.01)
+            .divergence_check(DivergenceCheck::PerIteration)
+            .fit(&data)
+            .unwrap();
+        assert_eq!(model.rows(), 20);
+        assert_eq!(model.columns(), 10);
+        assert!(model.evaluator().unwrap().rmse(&data).unwrap() < 0.2);
+    }
+
+    #[test]
+    fn test_als_implicit() {
+        let data = generate_implicit();
+        let model = Model::params()
+            .quiet(true)
+            .loss(Loss::OneClassL2)
+            .solver(Solver::Als)
+            .fit(&data)
+            .unwrap();
+        for i in 0..20 {
+            let same = model.predict(i, i);
+            let different = model.predict(i, (i + 1) % 20);
+            assert!(same > different + 0.25);
+        }
+    }
+
+    #[test]
+    fn test_als_weights() {
+        let mut data = Matrix::new();
+        data.push_weighted(0, 0, 1.0, 3.0);
+        data.push_weighted(0, 0, 0.0, 1.0);
+        let model = Model::params()
+            .quiet(true)
+            .factors(1)
+            .solver(Solver::Als)
+            .lambda_p2(0.0)
+            .lambda_q2(0.0)
+            .fit(&data)
+            .unwrap();
+        assert!((model.predict(0, 0) - 0.75).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_als_confidence() {
+        // rows 0 and 2 have the same pattern, but the entries of row 0 have a higher confidence
+        let mut data = Matrix::new();
+        for x in &generate_implicit().data {
+            let weight = if x.u == 0 { 20.0 } else { 1.0 };
+            data.push_weighted(x.u, x.v, x.r, weight);
+        }
+        let model = Model::params()
+            .quiet(true)
+            .loss(Loss::OneClassL2)
+            .solver(Solver::Als)
+            .fit(&data)
+            .unwrap();
+
+        let error = |row: i32| -> f32 {
+            data.data
+                .iter()
+                .filter(|x| x.u == row)
+                .map(|x| (1.0 - model.predict(x.u, x.v)).abs())
+                .sum()
+        };
+        assert!(error(0) < error(2));
+    }
+
+    #[test]
+    fn test_als_threads() {
+        let data = generate_data();
+        let mut params = Model::params();
+        params.quiet(true).solver(Solver::Als);
+        let model = params.fit(&data).unwrap();
+        let model2 = params.threads(4).fit(&data).unwrap();
+        assert_eq!(model.p_factors(), model2.p_factors());
+        assert_eq!(model.q_factors(), model2.q_factors());
+    }
+
+    #[test]
+    fn test_als_nmf() {
+        let data = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::Als)
+            .nmf(true)
+            .fit(&data)
+            .unwrap();
+        assert!(model.p_factors().iter().all(|&v| v >= 0.0));
+        assert!(model.q_factors().iter().all(|&v| v >= 0.0));
+    }
+
+    #[test]
+    fn test_als_fit_eval() {
+        let data = generate_data();
+        let mut eval_set = Matrix::new();
+        eval_set.push(25, 0, 1.0);
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::Als)
+            .fit_eval(&data, &eval_set)
+            .unwrap();
+        assert_eq!(model.rows(), 26);
+        assert!(!model.is_known_row(25));
+        assert!(model.p(25).unwrap().iter().all(|v| v.is_nan()));
+    }
+
+    #[test]
+    fn test_als_errors() {
+        let data = generate_data();
+        let mut params = Model::params();
+        params.solver(Solver::Als);
+
+        let result = params.clone().loss(Loss::BinaryLog).fit(&data);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::SolverLoss(Solver::Als, Loss::BinaryLog).into()
+        );
+
+        let result = params.clone().lambda_q1(0.1).fit(&data);
+        assert_eq!(result.unwrap_err(), ParameterError::AlsL1.into());
+
+        let result = params.cv(&data, 2);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::SolverCv(Solver::Als).into()
+        );
+
+        let result = params.fit_hybrid(&data, &Matrix::new(), &Matrix::new());
+        assert_eq!(result.unwrap_err(), ParameterError::AlsFeatures.into());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::{Error, Matrix, Model, RecordBatchError};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float32Type, Float64Type, Int32Type};
+use arrow_array::{Array, ArrayRef, FixedSizeListArray, Float32Array, Int32Array, RecordBatch};
+use arrow_cast::{cast_with_options, CastOptions};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+
+impl Matrix {
+    /// Creates a matrix from an Arrow record batch.
+    ///
+    /// Columns are cast to the right type, and nulls are an error. Float row and column
+    /// indices must be integers that fit in an `i32`.
+    pub fn from_record_batch(
+        batch: &RecordBatch,
+        row_column: &str,
+        column_column: &str,
+        value_column: &str,
+    ) -> Result<Matrix, Error> {
+        let mut matrix = Matrix::with_capacity(batch.num_rows());
+        matrix.extend_from_record_batch(batch, row_column, column_column, value_column)?;
+        Ok(matrix)
+    }
+
+    /// Creates a matrix from a stream of Arrow record batches (like from a Parquet file).
+    ///
+    /// Columns are cast to the right type, and nulls are an error. Float row and column
+    /// indices must be integers that fit in an `i32`.
+    pub fn from_record_batches<I: IntoIterator<Item = Result<RecordBatch, ArrowError>>>(
+        batches: I,
+        row_column: &str,
+        column_column: &str,
+        value_column: &str,
+    ) -> Result<Matrix, Error> {
+        let mut matrix = Matrix::new();
+        for batch in batches {
+            let batch = batch.map_err(RecordBatchError::Arrow)?;
+            matrix.extend_from_record_batch(&batch, row_column, column_column, value_column)?;
+        }
+        Ok(matrix)
+    }
+
+    fn extend_from_record_batch(
+        &mut self,
+        batch: &RecordBatch,
+        row_column: &str,
+        column_column: &str,
+        value_column: &str,
+    ) -> Result<(), Error> {
+        // report null positions across batches
+        let offset = self.len();
+        let rows = column(batch, row_column, &DataType::Int32, offset)?;
+        let columns = column(batch, column_column, &DataType::Int32, offset)?;
+        let values = column(batch, value_column, &DataType::Float32, offset)?;
+
+        let rows = rows.as_primitive::<Int32Type>().values();
+        let columns = columns.as_primitive::<Int32Type>().values();
+        let values = values.as_primitive::<Float32Type>().values();
+        self.data.reserve(rows.len());
+        for i in 0..rows.len() {
+            self.push(rows[i], columns[i], values[i]);
+        }
+        Ok(())
+    }
+}
+
+fn column(
+    batch: &RecordBatch,
+    name: &str,
+    data_type: &DataType,
+    offset: usize,
+) -> Result<ArrayRef, Error> {
+    let array = batch
+        .column_by_name(name)
+        .ok_or_else(|| RecordBatchError::MissingColumn(name.into()))?;
+    if array.null_count() > 0 {
+        let index = (0..array.len()).find(|&i| array.is_null(i)).unwrap();
+        return Err(RecordBatchError::Null {
+            column: name.into(),
+            row: offset + index,
+        }
+        .into());
+    }
+
+    // return an error instead of null when a value cannot be cast
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    let cast = |array: &ArrayRef, data_type: &DataType| {
+        cast_with_options(array, data_type, &options).map_err(|source| RecordBatchError::Cast {
+            column: name.into(),
+            source,
+        })
+    };
+
+    // casting floats to integers truncates, so check indices first
+    if data_type == &DataType::Int32 && array.data_type().is_floating() {
+        let floats = cast(array, &DataType::Float64)?;
+        let floats = floats.as_primitive::<Float64Type>().values();
+        if let Some(index) = floats.iter().position(|&x| !valid_index(x)) {
+            return Err(RecordBatchError::Index {
+                column: name.into(),
+                row: offset + index,
+            }
+            .into());
+        }
+    }
+
+    Ok(cast(array, data_type)?)
+}
+
+fn valid_index(x: f64) -> bool {
+    x.fract() == 0.0 && x >= i32::MIN as f64 && x <= i32::MAX as f64
+}
+
+impl Model {
+    /// Returns predictions for the entries of a matrix as an Arrow record batch.
+    ///
+    /// The record batch has `row`, `column`, and `prediction` columns.
+    pub fn predict_record_batch(&self, data: &Matrix) -> Result<RecordBatch, Error> {
+        let mut rows = Vec::with_capacity(data.len());
+        let mut columns = Vec::with_capacity(data.len());
+        let mut predictions = Vec::with_capacity(data.len());
+        for (row_index, column_index, _) in data {
+            rows.push(row_index);
+            columns.push(column_index);
+            predictions.push(self.predict(row_index, column_index));
+        }
+
+        let schema = Schema::new(vec![
+            Field::new("row", DataType::Int32, false),
+            Field::new("column", DataType::Int32, false),
+            Field::new("prediction", DataType::Float32, false),
+        ]);
+        let arrays: Vec<ArrayRef> = vec![
+            Arc::new(Int32Array::from(rows)),
+            Arc::new(Int32Array::from(columns)),
+            Arc::new(Float32Array::from(predictions)),
+        ];
+        Ok(RecordBatch::try_new(Arc::new(schema), arrays).map_err(RecordBatchError::Arrow)?)
+    }
+
+    /// Returns the latent factors for rows as an Arrow record batch.
+    ///
+    /// The record batch has `row` and `factors` columns.
+    pub fn p_record_batch(&self) -> Result<RecordBatch, Error> {
+        factors_record_batch("row", self.p_factors(), self.factors())
+    }
+
+    /// Returns the latent factors for columns as an Arrow record batch.
+    ///
+    /// The record batch has `column` and `factors` columns.
+    pub fn q_record_batch(&self) -> Result<RecordBatch, Error> {
+        factors_record_batch("column", self.q_factors(), self.factors())
+    }
+}
+
+fn factors_record_batch(name: &str, factors: &[f32], size: i32) -> Result<RecordBatch, Error> {
+    let arrow_error = RecordBatchError::Arrow;
+
+    let len = if size > 0 {
+        factors.len() / size as usize
+    } else {
+        0
+    };
+    let item = Arc::new(Field::new("item", DataType::Float32, false));
+    let values = Arc::new(Float32Array::from(factors.to_vec()));
+    let list =
+        FixedSizeListArray::try_new(item.clone(), size, values, None).map_err(arrow_error)?;
+
+    let schema = Schema::new(vec![
+        Field::new(name, DataType::Int32, false),
+        Field::new("factors", DataType::FixedSizeList(item, size), false),
+    ]);
+    let arrays: Vec<ArrayRef> = vec![
+        Arc::new(Int32Array::from_iter_values(0..len as i32)),
+        Arc::new(list),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), arrays).map_err(arrow_error)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Matrix, Model, RecordBatchError};
+    use alloc::string::ToString;
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float32Type, Int32Type};
+    use arrow_array::{ArrayRef, Float64Array, Int32Array, Int64Array, RecordBatch};
+    use arrow_schema::ArrowError;
+
+    fn generate_batch() -> RecordBatch {
+        RecordBatch::try_from_iter(vec![
+            (
+                "user_id",
+                Arc::new(Int64Array::from(vec![0, 1, 1])) as ArrayRef,
+            ),
+            (
+                "item_id",
+                Arc::new(Int32Array::from(vec![0, 0, 1])) as ArrayRef,
+            ),
+            (
+                "rating",
+                Arc::new(Float64Array::from(vec![1.0, 2.0, 1.0])) as ArrayRef,
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_record_batch() {
+        let batch = generate_batch();
+        let data = Matrix::from_record_batch(&batch, "user_id", "item_id", "rating").unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 0, 1.0), (1, 0, 2.0), (1, 1, 1.0)]);
+    }
+
+    #[test]
+    fn test_from_record_batches() {
+        let batches: Vec<Result<RecordBatch, ArrowError>> =
+            vec![Ok(generate_batch()), Ok(generate_batch().slice(1, 2))];
+        let data = Matrix::from_record_batches(batches, "user_id", "item_id", "rating").unwrap();
+        assert_eq!(data.len(), 5);
+    }
+
+    #[test]
+    fn test_from_record_batch_missing_column() {
+        let batch = generate_batch();
+        let err = Matrix::from_record_batch(&batch, "user_id", "missing", "rating")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            Error::Arrow(RecordBatchError::MissingColumn("missing".into()))
+        );
+        assert_eq!(err.to_string(), "column not found: missing");
+    }
+
+    #[test]
+    fn test_from_record_batch_null() {
+        let batch = RecordBatch::try_from_iter(vec![
+            ("u", Arc::new(Int32Array::from(vec![0, 1])) as ArrayRef),
+            (
+                "v",
+                Arc::new(Int32Array::from(vec![Some(0), None])) as ArrayRef,
+            ),
+            (
+                "r",
+                Arc::new(Float64Array::from(vec![1.0, 2.0])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let batches = vec![Ok(batch.clone()), Ok(batch)];
+        let err = Matrix::from_record_batches(batches, "u", "v", "r")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            Error::Arrow(RecordBatchError::Null {
+                column: "v".into(),
+                row: 1
+            })
+        );
+        assert_eq!(err.to_string(), "column v has a null at row 1");
+    }
+
+    #[test]
+    fn test_from_record_batch_overflow() {
+        let batch = RecordBatch::try_from_iter(vec![
+            ("u", Arc::new(Int64Array::from(vec![i64::MAX])) as ArrayRef),
+            ("v", Arc::new(Int32Array::from(vec![0])) as ArrayRef),
+            ("r", Arc::new(Float64Array::from(vec![1.0])) as ArrayRef),
+        ])
+        .unwrap();
+        let err = Matrix::from_record_batch(&batch, "u", "v", "r")
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "cannot cast column u");
+        match err {
+            Error::Arrow(RecordBatchError::Cast { column, source }) => {
+                assert_eq!(column, "u");
+                assert!(matches!(source, ArrowError::CastError(_)));
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_from_record_batch_float_index() {
+        let batch = RecordBatch::try_from_iter(vec![
+            (
+                "u",
+                Arc::new(Float64Array::from(vec![0.0, 1.0])) as ArrayRef,
+            ),
+            ("v", Arc::new(Int32Array::from(vec![0, 1])) as ArrayRef),
+            (
+                "r",
+                Arc::new(Float64Array::from(vec![1.0, 2.0])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+        let data = Matrix::from_record_batch(&batch, "u", "v", "r").unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 0, 1.0), (1, 1, 2.0)]);
+
+        for value in [1.5, 3e9, f64::NAN, f64::INFINITY] {
+            let batch = RecordBatch::try_from_iter(vec![
+                ("u", Arc::new(Int32Array::from(vec![0, 1])) as ArrayRef),
+                (
+                    "v",
+                    Arc::new(Float64Array::from(vec![0.0, value])) as ArrayRef,
+                ),
+                (
+                    "r",
+                    Arc::new(Float64Array::from(vec![1.0, 2.0])) as ArrayRef,
+                ),
+            ])
+            .unwrap();
+            let err =
+                Matrix::from_record_batches(vec![Ok(batch.clone()), Ok(batch)], "u", "v", "r")
+                    .err()
+                    .unwrap();
+            assert_eq!(
+                err,
+                Error::Arrow(RecordBatchError::Index {
+                    column: "v".into(),
+                    row: 1
+                })
+            );
+            assert_eq!(err.to_string(), "column v has an invalid index at row 1");
+        }
+    }
+
+    #[test]
+    fn test_record_batch_export() {
+        let batch = generate_batch();
+        let data = Matrix::from_record_batch(&batch, "user_id", "item_id", "rating").unwrap();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+
+        let predictions = model.predict_record_batch(&data).unwrap();
+        assert_eq!(predictions.num_rows(), 3);
+        let values = predictions.column(2).as_primitive::<Float32Type>();
+        assert_eq!(values.value(1), model.predict(1, 0));
+
+        let p = model.p_record_batch().unwrap();
+        assert_eq!(p.num_rows(), 2);
+        assert_eq!(p.schema().field(0).name(), "row");
+        let rows = p.column(0).as_primitive::<Int32Type>();
+        assert_eq!(rows.values().as_ref(), [0, 1]);
+        let factors = p.column(1).as_fixed_size_list().value(1);
+        let factors = factors.as_primitive::<Float32Type>();
+        assert_eq!(factors.values().as_ref(), model.p(1).unwrap());
+
+        let q = model.q_record_batch().unwrap();
+        assert_eq!(q.num_rows(), 2);
+        assert_eq!(q.schema().field(0).name(), "column");
+    }
+}
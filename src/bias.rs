@@ -0,0 +1,299 @@
+use crate::bindings::MfNode;
+use crate::Matrix;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// row and column bias terms (empty when disabled)
+#[derive(Debug, Default)]
+pub(crate) struct Biases {
+    pub(crate) rows: Vec<f32>,
+    pub(crate) columns: Vec<f32>,
+}
+
+impl Biases {
+    // zero for every row and column, for solvers that learn the biases with the factors
+    pub(crate) fn new(row_bias: bool, column_bias: bool, rows: usize, columns: usize) -> Self {
+        Biases {
+            rows: if row_bias {
+                vec![0.0; rows]
+            } else {
+                Vec::new()
+            },
+            columns: if column_bias {
+                vec![0.0; columns]
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    // regularized least squares for each side, with the factors and the other side fixed
+    pub(crate) fn solve<F: Fn(&MfNode) -> f32>(&mut self, data: &Matrix, dot: F, lambda: f32) {
+        if !self.rows.is_empty() {
+            let mut sums = vec![0.0; self.rows.len()];
+            let mut weights = vec![0.0; self.rows.len()];
+            for (i, x) in data.data.iter().enumerate() {
+                let w = data.weight(i);
+                sums[x.u as usize] += w * (x.r - dot(x) - self.column(x.v));
+                weights[x.u as usize] += w;
+            }
+            update(&mut self.rows, &sums, &weights, lambda);
+        }
+        if !self.columns.is_empty() {
+            let mut sums = vec![0.0; self.columns.len()];
+            let mut weights = vec![0.0; self.columns.len()];
+            for (i, x) in data.data.iter().enumerate() {
+                let w = data.weight(i);
+                sums[x.v as usize] += w * (x.r - dot(x) - self.row(x.u));
+                weights[x.v as usize] += w;
+            }
+            update(&mut self.columns, &sums, &weights, lambda);
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rows.is_empty() && self.columns.is_empty()
+    }
+
+    // matches the dimensions of the model, which can be larger with an eval set
+    pub(crate) fn resize(&mut self, rows: i32, columns: i32) {
+        if !self.rows.is_empty() {
+            self.rows.resize(rows.max(0) as usize, 0.0);
+        }
+        if !self.columns.is_empty() {
+            self.columns.resize(columns.max(0) as usize, 0.0);
+        }
+    }
+
+    pub(crate) fn row(&self, row_index: i32) -> f32 {
+        get(&self.rows, row_index)
+    }
+
+    pub(crate) fn column(&self, column_index: i32) -> f32 {
+        get(&self.columns, column_index)
+    }
+
+    pub(crate) fn predict(&self, row_index: i32, column_index: i32) -> f32 {
+        self.row(row_index) + self.column(column_index)
+    }
+}
+
+pub(crate) fn get(biases: &[f32], index: i32) -> f32 {
+    if index < 0 {
+        return 0.0;
+    }
+    biases.get(index as usize).copied().unwrap_or(0.0)
+}
+
+// one gradient step, where `g` is the negative gradient of the loss for the entry
+pub(crate) fn step(bias: &mut f32, g: f32, weight: f32, eta: f32, lambda: f32) {
+    *bias += eta * weight * (g - lambda * *bias);
+}
+
+// the weights are the number of entries when there are no weights, and the
+// regularization is per entry like the gradient steps
+fn update(biases: &mut [f32], sums: &[f32], weights: &[f32], lambda: f32) {
+    for ((b, &sum), &weight) in biases.iter_mut().zip(sums).zip(weights) {
+        *b = if weight > 0.0 {
+            sum / ((1.0 + lambda) * weight)
+        } else {
+            0.0
+        };
+    }
+}
+
+pub(crate) fn weighted_mean(data: &Matrix) -> f32 {
+    if data.weights.is_empty() {
+        return data.mean().unwrap_or(0.0);
+    }
+    let mut sum = 0.0;
+    let mut total = 0.0;
+    for (x, &w) in data.data.iter().zip(&data.weights) {
+        sum += w as f64 * x.r as f64;
+        total += w as f64;
+    }
+    if total > 0.0 {
+        (sum / total) as f32
+    } else {
+        0.0
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) use file::BiasReader;
+
+#[cfg(feature = "std")]
+mod file {
+    use super::Biases;
+    use crate::{Error, IoError};
+    use std::fs::OpenOptions;
+    use std::io::{BufWriter, Write};
+
+    // LIBMF stops reading after the factors, so older versions can still load the file
+    const ROW_PREFIX: &str = "rb";
+    const COLUMN_PREFIX: &str = "cb";
+
+    impl Biases {
+        pub(crate) fn append(&self, path: &str) -> Result<(), Error> {
+            let io_error = |e| Error::Io(IoError::write(path).with_source(e));
+            let file = OpenOptions::new()
+                .append(true)
+                .open(path)
+                .map_err(io_error)?;
+            let mut w = BufWriter::new(file);
+            for (prefix, biases) in [(ROW_PREFIX, &self.rows), (COLUMN_PREFIX, &self.columns)] {
+                for (i, b) in biases.iter().enumerate() {
+                    writeln!(w, "{}{} {}", prefix, i, b).map_err(io_error)?;
+                }
+            }
+            w.flush().map_err(io_error)
+        }
+    }
+
+    // reads the biases a line at a time, along with the other sections
+    pub(crate) struct BiasReader<'a> {
+        path: &'a str,
+        rows: usize,
+        columns: usize,
+        biases: Biases,
+        last: usize,
+    }
+
+    impl<'a> BiasReader<'a> {
+        pub(crate) fn new(path: &'a str, rows: i32, columns: i32) -> Self {
+            Self {
+                path,
+                rows: rows.max(0) as usize,
+                columns: columns.max(0) as usize,
+                biases: Biases::default(),
+                last: 0,
+            }
+        }
+
+        pub(crate) fn read_line(&mut self, number: usize, line: &str) -> Result<(), Error> {
+            let malformed = || Error::MalformedModel {
+                path: self.path.into(),
+                line: Some(number),
+            };
+            let (values, len, rest) = if let Some(rest) = line.strip_prefix(ROW_PREFIX) {
+                (&mut self.biases.rows, self.rows, rest)
+            } else if let Some(rest) = line.strip_prefix(COLUMN_PREFIX) {
+                (&mut self.biases.columns, self.columns, rest)
+            } else {
+                return Ok(());
+            };
+            let (index, value) = rest.split_once(' ').ok_or_else(malformed)?;
+            let index: usize = index.parse().map_err(|_| malformed())?;
+            let value: f32 = value.parse().map_err(|_| malformed())?;
+            // written in order
+            if index != values.len() || index >= len {
+                return Err(malformed());
+            }
+            values.push(value);
+            self.last = number;
+            Ok(())
+        }
+
+        pub(crate) fn finish(self) -> Result<Biases, Error> {
+            // a section has a bias for every row or column
+            let rows = &self.biases.rows;
+            let columns = &self.biases.columns;
+            if (!rows.is_empty() && rows.len() != self.rows)
+                || (!columns.is_empty() && columns.len() != self.columns)
+            {
+                return Err(Error::MalformedModel {
+                    path: self.path.into(),
+                    line: Some(self.last),
+                });
+            }
+            Ok(self.biases)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{step, Biases};
+    use crate::Matrix;
+
+    fn assert_in_delta(expected: f32, actual: f32) {
+        assert!(
+            (expected - actual).abs() < 1e-4,
+            "expected {} to be close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_solve_rows() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 4.0);
+        data.push(0, 1, 4.0);
+        data.push(1, 0, 2.0);
+        data.push(1, 1, 2.0);
+        let mut biases = Biases::new(true, false, 2, 2);
+        biases.solve(&data, |_| 3.0, 0.0);
+        assert_eq!(biases.rows, [1.0, -1.0]);
+        assert!(biases.columns.is_empty());
+    }
+
+    #[test]
+    fn test_solve_both() {
+        // additive data is fit exactly without regularization
+        let mut data = Matrix::new();
+        let row_effects = [1.0, -0.5, 0.0];
+        let column_effects = [0.5, -0.5];
+        for (u, a) in row_effects.iter().enumerate() {
+            for (v, b) in column_effects.iter().enumerate() {
+                data.push(u as i32, v as i32, 3.0 + a + b);
+            }
+        }
+        let mut biases = Biases::new(true, true, 3, 2);
+        for _ in 0..20 {
+            biases.solve(&data, |_| 3.0, 0.0);
+        }
+        for (u, v, r) in &data {
+            assert_in_delta(r, 3.0 + biases.predict(u, v));
+        }
+    }
+
+    #[test]
+    fn test_solve_regularization() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 3.0);
+        data.push(1, 0, 1.0);
+        let mut biases = Biases::new(true, false, 2, 1);
+        biases.solve(&data, |_| 2.0, 1.0);
+        assert_eq!(biases.rows, [0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_solve_weighted() {
+        let mut data = Matrix::new();
+        data.push_weighted(0, 0, 4.0, 3.0);
+        data.push_weighted(0, 1, 0.0, 1.0);
+        let mut biases = Biases::new(true, false, 1, 2);
+        biases.solve(&data, |_| 0.0, 0.0);
+        // weighted mean is 3
+        assert_eq!(biases.rows, [3.0]);
+    }
+
+    #[test]
+    fn test_step() {
+        let mut bias = 1.0;
+        step(&mut bias, 2.0, 0.5, 0.1, 1.0);
+        assert_in_delta(1.05, bias);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut biases = Biases::new(true, false, 2, 1);
+        biases.resize(3, 2);
+        assert_eq!(biases.rows.len(), 3);
+        assert!(biases.columns.is_empty());
+        assert_eq!(biases.row(2), 0.0);
+        assert_eq!(biases.row(-1), 0.0);
+        assert_eq!(biases.row(100), 0.0);
+    }
+}
@@ -0,0 +1,750 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use libmf::{DivergenceCheck, LearningRateSchedule, Loss, Matrix, Model, Params, Solver};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Train, evaluate, and inspect LIBMF models
+#[derive(Parser)]
+#[command(name = "libmf-cli", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Train a model
+    Train {
+        /// The training set (LIBMF text or CSV)
+        train_file: String,
+        /// Where to save the model [default: <train_file>.model]
+        model_file: Option<String>,
+        /// The evaluation set (LIBMF text or CSV)
+        #[arg(short = 'p', long)]
+        eval: Option<String>,
+        /// The model format [default: csv for .csv files, otherwise libmf]
+        #[arg(long, value_enum)]
+        format: Option<ModelFormat>,
+        #[command(flatten)]
+        params: ParamArgs,
+    },
+    /// Predict values for entries
+    Predict {
+        /// The entries to predict (LIBMF text or CSV)
+        test_file: String,
+        /// The model
+        model_file: String,
+        /// Where to write predictions [default: stdout]
+        output_file: Option<String>,
+    },
+    /// Calculate the metrics relevant to the loss function
+    Eval {
+        /// The evaluation set (LIBMF text or CSV)
+        test_file: String,
+        /// The model
+        model_file: String,
+    },
+    /// Perform cross-validation
+    Cv {
+        /// The training set (LIBMF text or CSV)
+        train_file: String,
+        /// The number of folds
+        #[arg(short = 'v', long, default_value = "5")]
+        folds: i32,
+        #[command(flatten)]
+        params: ParamArgs,
+    },
+    /// Recommend columns for a row
+    Recommend {
+        /// The model
+        model_file: String,
+        /// The row index
+        #[arg(long)]
+        row: i32,
+        /// The number of recommendations
+        #[arg(long, default_value = "10")]
+        count: usize,
+        /// Exclude columns the row has entries for in this file (LIBMF text or CSV)
+        #[arg(long)]
+        exclude: Option<String>,
+    },
+    /// Show information about a model
+    Inspect {
+        /// The model
+        model_file: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum ModelFormat {
+    /// LIBMF text format with biases, training counts, and metadata
+    Libmf,
+    /// CSV with loss, bias, factors, and biases (for export only)
+    Csv,
+}
+
+#[derive(Args)]
+struct ParamArgs {
+    /// The loss function (name or LIBMF number)
+    #[arg(short = 'l', long, value_parser = parse_loss)]
+    loss: Option<Loss>,
+    /// The number of latent factors
+    #[arg(short = 'k', long)]
+    factors: Option<i32>,
+    /// The number of threads
+    #[arg(short = 's', long)]
+    threads: Option<i32>,
+    /// The number of bins
+    #[arg(short = 'n', long)]
+    bins: Option<i32>,
+    /// The number of iterations
+    #[arg(short = 't', long)]
+    iterations: Option<i32>,
+    /// The L1-regularization parameter for P
+    #[arg(long)]
+    lambda_p1: Option<f32>,
+    /// The L2-regularization parameter for P
+    #[arg(long)]
+    lambda_p2: Option<f32>,
+    /// The L1-regularization parameter for Q
+    #[arg(long)]
+    lambda_q1: Option<f32>,
+    /// The L2-regularization parameter for Q
+    #[arg(long)]
+    lambda_q2: Option<f32>,
+    /// The learning rate
+    #[arg(short = 'r', long)]
+    learning_rate: Option<f32>,
+    /// The importance of negative entries
+    #[arg(short = 'a', long)]
+    alpha: Option<f32>,
+    /// The desired value of negative entries
+    #[arg(short = 'c', long)]
+    c: Option<f32>,
+    /// Perform non-negative MF (NMF)
+    #[arg(long)]
+    nmf: bool,
+    /// No outputs to stdout
+    #[arg(short = 'q', long)]
+    quiet: bool,
+    /// Check the training loss for divergence (aftertraining, which is the default without a
+    /// value, or periteration)
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "aftertraining",
+        value_parser = parse_divergence_check
+    )]
+    divergence_check: Option<DivergenceCheck>,
+    /// The number of times to retry with half the learning rate after divergence
+    #[arg(long)]
+    divergence_retries: Option<u32>,
+    /// Learn a bias for each row
+    #[arg(long)]
+    row_bias: bool,
+    /// Learn a bias for each column
+    #[arg(long)]
+    column_bias: bool,
+    /// The L2-regularization parameter for the biases
+    #[arg(long)]
+    lambda_bias: Option<f32>,
+    /// The solver (sgd, rustsgd, or als)
+    #[arg(long, value_parser = parse_solver)]
+    solver: Option<Solver>,
+    /// The learning rate schedule (constant, step:<step_size>:<gamma>, exponential:<gamma>,
+    /// cosine:<min_learning_rate>, or warmupcosine:<warmup>:<min_learning_rate>),
+    /// which requires --solver rustsgd
+    #[arg(long, value_parser = parse_schedule)]
+    learning_rate_schedule: Option<LearningRateSchedule>,
+}
+
+impl ParamArgs {
+    fn params(&self) -> Params {
+        let mut params = Model::params();
+        if let Some(value) = self.loss {
+            params.loss(value);
+        }
+        if let Some(value) = self.factors {
+            params.factors(value);
+        }
+        if let Some(value) = self.threads {
+            params.threads(value);
+        }
+        if let Some(value) = self.bins {
+            params.bins(value);
+        }
+        if let Some(value) = self.iterations {
+            params.iterations(value);
+        }
+        if let Some(value) = self.lambda_p1 {
+            params.lambda_p1(value);
+        }
+        if let Some(value) = self.lambda_p2 {
+            params.lambda_p2(value);
+        }
+        if let Some(value) = self.lambda_q1 {
+            params.lambda_q1(value);
+        }
+        if let Some(value) = self.lambda_q2 {
+            params.lambda_q2(value);
+        }
+        if let Some(value) = self.learning_rate {
+            params.learning_rate(value);
+        }
+        if let Some(value) = self.alpha {
+            params.alpha(value);
+        }
+        if let Some(value) = self.c {
+            params.c(value);
+        }
+        if let Some(value) = self.divergence_check {
+            params.divergence_check(value);
+        }
+        if let Some(value) = self.divergence_retries {
+            params.divergence_retries(value);
+        }
+        if let Some(value) = self.lambda_bias {
+            params.lambda_bias(value);
+        }
+        if let Some(value) = self.solver {
+            params.solver(value);
+        }
+        if let Some(value) = self.learning_rate_schedule {
+            params.learning_rate_schedule(value);
+        }
+        params
+            .nmf(self.nmf)
+            .quiet(self.quiet)
+            .row_bias(self.row_bias)
+            .column_bias(self.column_bias);
+        params
+    }
+}
+
+const LOSSES: [Loss; 9] = [
+    Loss::RealL2,
+    Loss::RealL1,
+    Loss::RealKL,
+    Loss::BinaryLog,
+    Loss::BinaryL2,
+    Loss::BinaryL1,
+    Loss::OneClassRow,
+    Loss::OneClassCol,
+    Loss::OneClassL2,
+];
+
+fn parse_loss(value: &str) -> std::result::Result<Loss, String> {
+    LOSSES
+        .into_iter()
+        .find(|&loss| {
+            value == (loss as i32).to_string() || value.eq_ignore_ascii_case(&format!("{:?}", loss))
+        })
+        .ok_or_else(|| format!("unknown loss function: {}", value))
+}
+
+const SOLVERS: [Solver; 3] = [Solver::Sgd, Solver::RustSgd, Solver::Als];
+
+fn parse_solver(value: &str) -> std::result::Result<Solver, String> {
+    SOLVERS
+        .into_iter()
+        .find(|&solver| value.eq_ignore_ascii_case(&format!("{:?}", solver)))
+        .ok_or_else(|| format!("unknown solver: {}", value))
+}
+
+const DIVERGENCE_CHECKS: [DivergenceCheck; 3] = [
+    DivergenceCheck::None,
+    DivergenceCheck::AfterTraining,
+    DivergenceCheck::PerIteration,
+];
+
+fn parse_divergence_check(value: &str) -> std::result::Result<DivergenceCheck, String> {
+    DIVERGENCE_CHECKS
+        .into_iter()
+        .find(|&check| value.eq_ignore_ascii_case(&format!("{:?}", check)))
+        .ok_or_else(|| format!("unknown divergence check: {}", value))
+}
+
+// the name of the schedule followed by its parameters, separated by colons
+fn parse_schedule(value: &str) -> std::result::Result<LearningRateSchedule, String> {
+    let invalid = || format!("invalid learning rate schedule: {}", value);
+    let fields: Vec<&str> = value.split(':').collect();
+    let name = fields[0].to_ascii_lowercase();
+    let schedule = match (name.as_str(), &fields[1..]) {
+        ("constant", []) => LearningRateSchedule::Constant,
+        ("step", [step_size, gamma]) => LearningRateSchedule::Step {
+            step_size: step_size.parse().map_err(|_| invalid())?,
+            gamma: gamma.parse().map_err(|_| invalid())?,
+        },
+        ("exponential", [gamma]) => LearningRateSchedule::Exponential {
+            gamma: gamma.parse().map_err(|_| invalid())?,
+        },
+        ("cosine", [min_learning_rate]) => LearningRateSchedule::Cosine {
+            min_learning_rate: min_learning_rate.parse().map_err(|_| invalid())?,
+        },
+        ("warmupcosine", [warmup, min_learning_rate]) => LearningRateSchedule::WarmupCosine {
+            warmup: warmup.parse().map_err(|_| invalid())?,
+            min_learning_rate: min_learning_rate.parse().map_err(|_| invalid())?,
+        },
+        _ => return Err(invalid()),
+    };
+    Ok(schedule)
+}
+
+fn is_csv(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+fn open(path: &str) -> Result<BufReader<File>> {
+    let file = File::open(path).map_err(|e| format!("cannot read file: {} ({})", path, e))?;
+    Ok(BufReader::new(file))
+}
+
+// reads `row column value` lines, or `row,column,value` lines for CSV (with an optional header)
+fn read_matrix(path: &str) -> Result<Matrix> {
+    let csv = is_csv(path);
+    let mut data = Matrix::new();
+    for (i, line) in open(path)?.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = if csv {
+            line.split(',').map(|v| v.trim()).collect()
+        } else {
+            line.split_whitespace().collect()
+        };
+        let entry = match fields[..] {
+            [row, column, value] => row
+                .parse()
+                .ok()
+                .zip(column.parse().ok())
+                .zip(value.parse().ok()),
+            _ => None,
+        };
+        match entry {
+            Some(((row, column), value)) => data.push(row, column, value),
+            None if csv && i == 0 => continue,
+            None => return Err(format!("invalid entry: {} (line {})", path, i + 1).into()),
+        }
+    }
+    Ok(data)
+}
+
+// writes `loss`, `bias`, `p`, `q`, `rb`, and `cb` lines, with the index before the values
+fn write_csv_model(model: &Model, path: &str) -> Result<()> {
+    let file = File::create(path).map_err(|e| format!("cannot write file: {} ({})", path, e))?;
+    let mut w = BufWriter::new(file);
+    writeln!(w, "loss,{:?}", model.loss())?;
+    writeln!(w, "bias,{}", model.bias())?;
+    for (side, factors) in [("p", model.p_iter()), ("q", model.q_iter())] {
+        for (i, f) in factors.enumerate() {
+            write!(w, "{},{}", side, i)?;
+            for v in f {
+                write!(w, ",{}", v)?;
+            }
+            writeln!(w)?;
+        }
+    }
+    for i in 0..model.rows() {
+        if let Some(b) = model.row_bias(i) {
+            writeln!(w, "rb,{},{}", i, b)?;
+        }
+    }
+    for j in 0..model.columns() {
+        if let Some(b) = model.column_bias(j) {
+            writeln!(w, "cb,{},{}", j, b)?;
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn train(
+    train_file: &str,
+    model_file: Option<String>,
+    eval: Option<String>,
+    format: Option<ModelFormat>,
+    params: &ParamArgs,
+) -> Result<()> {
+    let model_file = model_file.unwrap_or_else(|| format!("{}.model", train_file));
+    let train_set = read_matrix(train_file)?;
+    let params = params.params();
+    let model = match eval {
+        Some(eval) => params.fit_eval(&train_set, &read_matrix(&eval)?)?,
+        None => params.fit(&train_set)?,
+    };
+    let format = format.unwrap_or(if is_csv(&model_file) {
+        ModelFormat::Csv
+    } else {
+        ModelFormat::Libmf
+    });
+    match format {
+        ModelFormat::Libmf => model.save(&model_file)?,
+        ModelFormat::Csv => write_csv_model(&model, &model_file)?,
+    }
+    Ok(())
+}
+
+fn predict(test_file: &str, model_file: &str, output_file: Option<String>) -> Result<()> {
+    let model = Model::load(model_file)?;
+    let data = read_matrix(test_file)?;
+
+    let mut w: Box<dyn Write> = match output_file {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(&path).map_err(|e| format!("cannot write file: {} ({})", path, e))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    for (row_index, column_index, _) in &data {
+        writeln!(w, "{}", model.predict(row_index, column_index))?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn eval(test_file: &str, model_file: &str) -> Result<()> {
+    let model = Model::load(model_file)?;
+    let data = read_matrix(test_file)?;
+    let report = model.evaluate(&data)?;
+    println!("{}", report);
+    Ok(())
+}
+
+fn cv(train_file: &str, folds: i32, params: &ParamArgs) -> Result<()> {
+    let data = read_matrix(train_file)?;
+    let error = params.params().cv(&data, folds)?;
+    println!("cv error: {:.6}", error);
+    Ok(())
+}
+
+fn recommend(model_file: &str, row: i32, count: usize, exclude: Option<String>) -> Result<()> {
+    let model = Model::load(model_file)?;
+    if row < 0 || row >= model.rows() {
+        return Err(format!("row not in model: {}", row).into());
+    }
+
+    let mut excluded = vec![false; model.columns() as usize];
+    if let Some(path) = exclude {
+        for (row_index, column_index, _) in &read_matrix(&path)? {
+            if row_index == row && column_index >= 0 && column_index < model.columns() {
+                excluded[column_index as usize] = true;
+            }
+        }
+    }
+
+    let mut scores: Vec<(i32, f32)> = (0..model.columns())
+        .filter(|&j| !excluded[j as usize])
+        .map(|j| (j, model.predict(row, j)))
+        .collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    for (column_index, score) in scores.into_iter().take(count) {
+        println!("{}\t{}", column_index, score);
+    }
+    Ok(())
+}
+
+fn inspect(model_file: &str) -> Result<()> {
+    let model = Model::load(model_file)?;
+    println!("loss: {:?}", model.loss());
+    println!("rows: {}", model.rows());
+    println!("columns: {}", model.columns());
+    println!("factors: {}", model.factors());
+    println!("bias: {}", model.bias());
+    println!("row biases: {}", model.row_bias(0).is_some());
+    println!("column biases: {}", model.column_bias(0).is_some());
+    println!("{}", model.summary());
+
+    let metadata = model.metadata();
+    if let Some(params) = &metadata.params {
+        println!("params: {:?}", params);
+    }
+    if let Some(nnz) = metadata.nnz {
+        println!("nnz: {}", nnz);
+    }
+    if let Some(trained_at) = metadata.trained_at {
+        println!("trained_at: {}", trained_at);
+    }
+    if let Some(training_time) = metadata.training_time {
+        println!("training_time: {:.3}s", training_time);
+    }
+    if let Some(version) = &metadata.version {
+        println!("version: {}", version);
+    }
+    for (key, value) in &metadata.tags {
+        println!("tag {}: {}", key, value);
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Train {
+            train_file,
+            model_file,
+            eval,
+            format,
+            params,
+        } => train(&train_file, model_file, eval, format, &params),
+        Command::Predict {
+            test_file,
+            model_file,
+            output_file,
+        } => predict(&test_file, &model_file, output_file),
+        Command::Eval {
+            test_file,
+            model_file,
+        } => eval(&test_file, &model_file),
+        Command::Cv {
+            train_file,
+            folds,
+            params,
+        } => cv(&train_file, folds, &params),
+        Command::Recommend {
+            model_file,
+            row,
+            count,
+            exclude,
+        } => recommend(&model_file, row, count, exclude),
+        Command::Inspect { model_file } => inspect(&model_file),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprint!("error: {}", err);
+            let mut source = err.source();
+            while let Some(err) = source {
+                eprint!(" ({})", err);
+                source = err.source();
+            }
+            eprintln!();
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(name)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_parse_loss() {
+        assert_eq!(parse_loss("RealL2"), Ok(Loss::RealL2));
+        assert_eq!(parse_loss("oneclassl2"), Ok(Loss::OneClassL2));
+        assert_eq!(parse_loss("12"), Ok(Loss::OneClassL2));
+        assert!(parse_loss("3").is_err());
+    }
+
+    #[test]
+    fn test_read_matrix_csv() {
+        let path = temp_path("libmf-cli-test.csv");
+        std::fs::write(&path, "row,column,value\n0,1,2.5\n\n3, 4, 5\n").unwrap();
+        let data = read_matrix(&path).unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 1, 2.5), (3, 4, 5.0)]);
+
+        std::fs::write(&path, "0,1,2.5\n0,x,1\n").unwrap();
+        let err = read_matrix(&path).err().unwrap();
+        assert_eq!(err.to_string(), format!("invalid entry: {} (line 2)", path));
+    }
+
+    #[test]
+    fn test_parse_solver() {
+        assert_eq!(parse_solver("sgd"), Ok(Solver::Sgd));
+        assert_eq!(parse_solver("RustSgd"), Ok(Solver::RustSgd));
+        assert_eq!(parse_solver("als"), Ok(Solver::Als));
+        assert!(parse_solver("adam").is_err());
+    }
+
+    #[test]
+    fn test_parse_divergence_check() {
+        assert_eq!(
+            parse_divergence_check("PerIteration"),
+            Ok(DivergenceCheck::PerIteration)
+        );
+        assert_eq!(
+            parse_divergence_check("aftertraining"),
+            Ok(DivergenceCheck::AfterTraining)
+        );
+        assert!(parse_divergence_check("always").is_err());
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        assert_eq!(
+            parse_schedule("constant"),
+            Ok(LearningRateSchedule::Constant)
+        );
+        assert_eq!(
+            parse_schedule("step:5:0.5"),
+            Ok(LearningRateSchedule::Step {
+                step_size: 5,
+                gamma: 0.5
+            })
+        );
+        assert_eq!(
+            parse_schedule("WarmupCosine:3:0.001"),
+            Ok(LearningRateSchedule::WarmupCosine {
+                warmup: 3,
+                min_learning_rate: 0.001
+            })
+        );
+        assert!(parse_schedule("exponential").is_err());
+        assert!(parse_schedule("cosine:x").is_err());
+    }
+
+    #[test]
+    fn test_params() {
+        let cli = Cli::try_parse_from([
+            "libmf-cli",
+            "train",
+            "train.txt",
+            "--divergence-check",
+            "--divergence-retries",
+            "3",
+            "--row-bias",
+            "--lambda-bias",
+            "0.5",
+            "--solver",
+            "rustsgd",
+            "--learning-rate-schedule",
+            "exponential:0.9",
+        ])
+        .unwrap();
+        let params = match cli.command {
+            Command::Train { params, .. } => params.params(),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            params.get_divergence_check(),
+            DivergenceCheck::AfterTraining
+        );
+        assert_eq!(params.get_divergence_retries(), 3);
+        assert!(params.get_row_bias());
+        assert!(!params.get_column_bias());
+        assert_eq!(params.get_lambda_bias(), 0.5);
+        assert_eq!(params.get_solver(), Solver::RustSgd);
+        assert_eq!(
+            params.get_learning_rate_schedule(),
+            LearningRateSchedule::Exponential { gamma: 0.9 }
+        );
+    }
+
+    #[test]
+    fn test_train_biases() {
+        let train_file = temp_path("libmf-cli-train.txt");
+        let model_file = temp_path("libmf-cli-train.model");
+        std::fs::write(&train_file, "0 0 1\n1 0 2\n1 1 1\n").unwrap();
+        let cli = Cli::try_parse_from([
+            "libmf-cli",
+            "train",
+            &train_file,
+            &model_file,
+            "--quiet",
+            "--row-bias",
+            "--solver",
+            "rustsgd",
+        ])
+        .unwrap();
+        run(cli).unwrap();
+
+        let model = Model::load(&model_file).unwrap();
+        assert!(model.row_bias(1).is_some());
+        assert_eq!(model.column_bias(0), None);
+    }
+
+    struct CsvModel {
+        loss: String,
+        bias: f32,
+        p: Vec<f32>,
+        q: Vec<f32>,
+        row_bias: Vec<f32>,
+        column_bias: Vec<f32>,
+    }
+
+    fn read_csv_model(path: &str) -> CsvModel {
+        let mut model = CsvModel {
+            loss: String::new(),
+            bias: 0.0,
+            p: Vec::new(),
+            q: Vec::new(),
+            row_bias: Vec::new(),
+            column_bias: Vec::new(),
+        };
+        for line in std::fs::read_to_string(path).unwrap().lines() {
+            let fields: Vec<&str> = line.split(',').collect();
+            let values = fields[2..].iter().map(|v| v.parse::<f32>().unwrap());
+            match fields[0] {
+                "loss" => model.loss = fields[1].to_string(),
+                "bias" => model.bias = fields[1].parse().unwrap(),
+                "p" => model.p.extend(values),
+                "q" => model.q.extend(values),
+                "rb" => model.row_bias.extend(values),
+                "cb" => model.column_bias.extend(values),
+                side => panic!("unexpected line: {}", side),
+            }
+        }
+        model
+    }
+
+    #[test]
+    fn test_csv_model() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 0, 2.0);
+        data.push(1, 1, 1.0);
+        let model = Model::params()
+            .factors(2)
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .row_bias(true)
+            .column_bias(true)
+            .fit(&data)
+            .unwrap();
+
+        let path = temp_path("libmf-cli-model.csv");
+        write_csv_model(&model, &path).unwrap();
+        let loaded = read_csv_model(&path);
+        assert_eq!(loaded.loss, "RealL2");
+        assert_eq!(loaded.bias, model.bias());
+        assert_eq!(loaded.p, model.p_factors());
+        assert_eq!(loaded.q, model.q_factors());
+        let row_bias: Vec<f32> = (0..2).map(|i| model.row_bias(i).unwrap()).collect();
+        let column_bias: Vec<f32> = (0..2).map(|j| model.column_bias(j).unwrap()).collect();
+        assert_eq!(loaded.row_bias, row_bias);
+        assert_eq!(loaded.column_bias, column_bias);
+    }
+
+    #[test]
+    fn test_train_format() {
+        let train_file = temp_path("libmf-cli-format.txt");
+        let model_file = temp_path("libmf-cli-format.csv");
+        std::fs::write(&train_file, "0 0 1\n1 0 2\n1 1 1\n").unwrap();
+        let args = ["libmf-cli", "train", &train_file, &model_file, "--quiet"];
+
+        run(Cli::try_parse_from(args).unwrap()).unwrap();
+        assert_eq!(read_csv_model(&model_file).loss, "RealL2");
+
+        // the flag takes precedence over the extension
+        let cli = Cli::try_parse_from(args.into_iter().chain(["--format", "libmf"])).unwrap();
+        run(cli).unwrap();
+        assert!(Model::load(&model_file).is_ok());
+    }
+}
@@ -16,7 +16,7 @@ pub struct MfProblem {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub struct MfParameter {
     pub fun: Loss,
     pub k: c_int,
@@ -48,7 +48,8 @@ pub struct MfModel {
 
 /// Loss functions.
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Loss {
     /// Squared error (L2-norm).
     RealL2 = 0,
@@ -81,11 +82,12 @@ extern "C" {
         va: *const MfProblem,
         param: MfParameter,
     ) -> *mut MfModel;
-    pub fn mf_cross_validation(
+    pub fn mf_cross_validation_status(
         prob: *const MfProblem,
         nr_folds: c_int,
         param: MfParameter,
-    ) -> c_double;
+        out: *mut c_double,
+    ) -> c_int;
     pub fn mf_predict(model: *const MfModel, u: c_int, v: c_int) -> c_float;
     pub fn calc_rmse(prob: *const MfProblem, model: *const MfModel) -> c_double;
     pub fn calc_mae(prob: *const MfProblem, model: *const MfModel) -> c_double;
@@ -0,0 +1,231 @@
+use crate::bindings::MfNode;
+use crate::metrics::{EntryError, ErrorSum};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Error metrics for a group of entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupError {
+    /// The number of entries.
+    pub count: usize,
+    /// The root mean square error.
+    pub rmse: f64,
+    /// The mean absolute error.
+    pub mae: f64,
+    /// The logarithmic loss.
+    pub logloss: f64,
+}
+
+/// Error metrics for rows or columns with a similar number of training entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActivityBucket {
+    /// The minimum number of training entries (inclusive).
+    pub min_interactions: usize,
+    /// The maximum number of training entries (inclusive).
+    pub max_interactions: usize,
+    /// The error metrics.
+    pub error: GroupError,
+}
+
+/// Error metrics grouped by row, column, and activity.
+#[derive(Clone, Debug)]
+pub struct ErrorBreakdown {
+    rows: Vec<GroupError>,
+    columns: Vec<GroupError>,
+    row_activity: Vec<ActivityBucket>,
+    column_activity: Vec<ActivityBucket>,
+}
+
+impl ErrorBreakdown {
+    pub(crate) fn new<F: Fn(i32, i32) -> f32>(
+        data: &[MfNode],
+        train_data: &[MfNode],
+        predict: F,
+    ) -> Self {
+        let row_counts = counts(train_data.iter().map(|x| x.u));
+        let column_counts = counts(train_data.iter().map(|x| x.v));
+
+        let rows_len = data.iter().map(|x| x.u as usize + 1).max().unwrap_or(0);
+        let columns_len = data.iter().map(|x| x.v as usize + 1).max().unwrap_or(0);
+
+        let mut rows = vec![ErrorSum::default(); rows_len];
+        let mut columns = vec![ErrorSum::default(); columns_len];
+        let mut row_activity = Vec::new();
+        let mut column_activity = Vec::new();
+
+        for x in data {
+            let error = EntryError::new(x.r, predict(x.u, x.v));
+            let (u, v) = (x.u as usize, x.v as usize);
+
+            rows[u].add(&error);
+            columns[v].add(&error);
+
+            let bucket = bucket_index(row_counts.get(u).copied().unwrap_or(0));
+            if bucket >= row_activity.len() {
+                row_activity.resize(bucket + 1, ErrorSum::default());
+            }
+            row_activity[bucket].add(&error);
+
+            let bucket = bucket_index(column_counts.get(v).copied().unwrap_or(0));
+            if bucket >= column_activity.len() {
+                column_activity.resize(bucket + 1, ErrorSum::default());
+            }
+            column_activity[bucket].add(&error);
+        }
+
+        Self {
+            rows: rows.iter().map(|x| x.finish()).collect(),
+            columns: columns.iter().map(|x| x.finish()).collect(),
+            row_activity: buckets(&row_activity),
+            column_activity: buckets(&column_activity),
+        }
+    }
+
+    /// Returns the error metrics for each row.
+    pub fn rows(&self) -> &[GroupError] {
+        &self.rows
+    }
+
+    /// Returns the error metrics for each column.
+    pub fn columns(&self) -> &[GroupError] {
+        &self.columns
+    }
+
+    /// Returns the error metrics for a row.
+    pub fn row(&self, row_index: i32) -> Option<&GroupError> {
+        usize::try_from(row_index)
+            .ok()
+            .and_then(|i| self.rows.get(i))
+    }
+
+    /// Returns the error metrics for a column.
+    pub fn column(&self, column_index: i32) -> Option<&GroupError> {
+        usize::try_from(column_index)
+            .ok()
+            .and_then(|i| self.columns.get(i))
+    }
+
+    /// Returns the error metrics grouped by the number of training entries for the row.
+    pub fn row_activity(&self) -> &[ActivityBucket] {
+        &self.row_activity
+    }
+
+    /// Returns the error metrics grouped by the number of training entries for the column.
+    pub fn column_activity(&self) -> &[ActivityBucket] {
+        &self.column_activity
+    }
+}
+
+fn counts<I: Iterator<Item = i32>>(indices: I) -> Vec<usize> {
+    let mut counts = Vec::new();
+    for i in indices {
+        let i = i as usize;
+        if i >= counts.len() {
+            counts.resize(i + 1, 0);
+        }
+        counts[i] += 1;
+    }
+    counts
+}
+
+// buckets are 0, 1, 2-3, 4-7, 8-15, ...
+fn bucket_index(count: usize) -> usize {
+    (usize::BITS - count.leading_zeros()) as usize
+}
+
+fn bucket_range(index: usize) -> (usize, usize) {
+    if index == 0 {
+        (0, 0)
+    } else {
+        (1 << (index - 1), (1 << index) - 1)
+    }
+}
+
+fn buckets(sums: &[ErrorSum]) -> Vec<ActivityBucket> {
+    sums.iter()
+        .enumerate()
+        .filter(|(_, x)| x.count > 0)
+        .map(|(i, x)| {
+            let (min_interactions, max_interactions) = bucket_range(i);
+            ActivityBucket {
+                min_interactions,
+                max_interactions,
+                error: x.finish(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Matrix, Model};
+
+    fn generate_data() -> Matrix {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 0, 2.0);
+        data.push(1, 1, 1.0);
+        data
+    }
+
+    #[test]
+    fn test_error_breakdown() {
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+        let breakdown = model.error_breakdown(&data, &data).unwrap();
+
+        assert_eq!(breakdown.rows().len(), 2);
+        assert_eq!(breakdown.columns().len(), 2);
+
+        let row = breakdown.row(0).unwrap();
+        assert_eq!(row.count, 1);
+        let error = (1.0 - model.predict(0, 0) as f64).abs();
+        assert!((row.rmse - error).abs() < 1e-6);
+        assert!((row.mae - error).abs() < 1e-6);
+
+        assert_eq!(breakdown.row(1).unwrap().count, 2);
+        assert_eq!(breakdown.column(0).unwrap().count, 2);
+        assert_eq!(breakdown.column(1).unwrap().count, 1);
+        assert_eq!(breakdown.row(2), None);
+        assert_eq!(breakdown.row(-1), None);
+
+        let activity = breakdown.row_activity();
+        assert_eq!(activity.len(), 2);
+        assert_eq!(activity[0].min_interactions, 1);
+        assert_eq!(activity[0].max_interactions, 1);
+        assert_eq!(activity[0].error.count, 1);
+        assert_eq!(activity[1].min_interactions, 2);
+        assert_eq!(activity[1].max_interactions, 3);
+        assert_eq!(activity[1].error.count, 2);
+    }
+
+    #[test]
+    fn test_error_breakdown_cold() {
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+
+        let mut eval_set = Matrix::new();
+        eval_set.push(3, 0, 1.0);
+        let breakdown = model.error_breakdown(&eval_set, &data).unwrap();
+
+        assert_eq!(breakdown.rows().len(), 4);
+        assert_eq!(breakdown.row(0).unwrap().count, 0);
+        assert_eq!(breakdown.row(0).unwrap().rmse, 0.0);
+
+        let activity = breakdown.row_activity();
+        assert_eq!(activity.len(), 1);
+        assert_eq!(activity[0].min_interactions, 0);
+        assert_eq!(activity[0].max_interactions, 0);
+        assert_eq!(breakdown.column_activity()[0].min_interactions, 2);
+    }
+
+    #[test]
+    fn test_bucket_index() {
+        assert_eq!(super::bucket_index(0), 0);
+        assert_eq!(super::bucket_index(1), 1);
+        assert_eq!(super::bucket_index(2), 2);
+        assert_eq!(super::bucket_index(3), 2);
+        assert_eq!(super::bucket_index(4), 3);
+        assert_eq!(super::bucket_range(3), (4, 7));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{Error, Matrix};
+use core::ffi::c_int;
+
+// skips zeros and NaNs
+fn from_dense<I: Iterator<Item = (usize, usize, f32)>>(
+    rows: usize,
+    columns: usize,
+    values: I,
+) -> Result<Matrix, Error> {
+    // i32::MAX is not a valid index
+    if rows > c_int::MAX as usize || columns > c_int::MAX as usize {
+        return Err(Error::Shape { rows, columns });
+    }
+
+    let mut matrix = Matrix::new();
+    for (row_index, column_index, value) in values {
+        if value != 0.0 && !value.is_nan() {
+            matrix.push(row_index as c_int, column_index as c_int, value);
+        }
+    }
+    Ok(matrix)
+}
+
+#[cfg(feature = "ndarray")]
+mod ndarray_interop {
+    use crate::{Error, Matrix, Model};
+    use ndarray::ArrayView2;
+
+    impl Model {
+        /// Returns the latent factors for rows as an array with shape (rows, factors).
+        pub fn p_array(&self) -> ArrayView2<'_, f32> {
+            ArrayView2::from_shape(
+                (self.rows() as usize, self.factors() as usize),
+                self.p_factors(),
+            )
+            .unwrap()
+        }
+
+        /// Returns the latent factors for columns as an array with shape (columns, factors).
+        pub fn q_array(&self) -> ArrayView2<'_, f32> {
+            ArrayView2::from_shape(
+                (self.columns() as usize, self.factors() as usize),
+                self.q_factors(),
+            )
+            .unwrap()
+        }
+    }
+
+    impl Matrix {
+        /// Creates a matrix from a dense array, skipping zeros and NaNs.
+        ///
+        /// Returns an error if the array has more than `i32::MAX` rows or columns.
+        pub fn from_ndarray(array: ArrayView2<'_, f32>) -> Result<Matrix, Error> {
+            let (rows, columns) = array.dim();
+            super::from_dense(
+                rows,
+                columns,
+                array.indexed_iter().map(|((i, j), &value)| (i, j, value)),
+            )
+        }
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_interop {
+    use crate::{Error, Matrix, Model};
+    use nalgebra::{DMatrixView, Dim, Dyn, RawStorage};
+
+    impl Model {
+        /// Returns the latent factors for rows as a matrix view with shape (rows, factors).
+        pub fn p_matrix_view(&self) -> DMatrixView<'_, f32, Dyn, Dyn> {
+            let factors = self.factors() as usize;
+            DMatrixView::from_slice_with_strides(
+                self.p_factors(),
+                self.rows() as usize,
+                factors,
+                factors,
+                1,
+            )
+        }
+
+        /// Returns the latent factors for columns as a matrix view with shape (columns, factors).
+        pub fn q_matrix_view(&self) -> DMatrixView<'_, f32, Dyn, Dyn> {
+            let factors = self.factors() as usize;
+            DMatrixView::from_slice_with_strides(
+                self.q_factors(),
+                self.columns() as usize,
+                factors,
+                factors,
+                1,
+            )
+        }
+    }
+
+    impl Matrix {
+        /// Creates a matrix from a dense `nalgebra` matrix, skipping zeros and NaNs.
+        ///
+        /// Returns an error if the matrix has more than `i32::MAX` rows or columns.
+        pub fn from_nalgebra<R: Dim, C: Dim, S: RawStorage<f32, R, C>>(
+            mat: &nalgebra::Matrix<f32, R, C, S>,
+        ) -> Result<Matrix, Error> {
+            let (rows, columns) = mat.shape();
+            super::from_dense(
+                rows,
+                columns,
+                (0..rows).flat_map(|i| (0..columns).map(move |j| (i, j, mat[(i, j)]))),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Matrix, Model};
+    use alloc::vec::Vec;
+
+    fn generate_data() -> Matrix {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 0, 2.0);
+        data.push(1, 1, 1.0);
+        data
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_ndarray() {
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+
+        let p = model.p_array();
+        assert_eq!(p.shape(), [2, 8]);
+        assert_eq!(p.row(1).as_slice(), model.p(1));
+
+        let q = model.q_array();
+        assert_eq!(q.shape(), [2, 8]);
+        assert_eq!(q.row(1).as_slice(), model.q(1));
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_from_ndarray() {
+        let array = ndarray::arr2(&[[1.0, 2.0], [f32::NAN, 3.0]]);
+        let data = Matrix::from_ndarray(array.view()).unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 0, 1.0), (0, 1, 2.0), (1, 1, 3.0)]);
+
+        // non-standard layout
+        let data = Matrix::from_ndarray(array.t()).unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 0, 1.0), (1, 0, 2.0), (1, 1, 3.0)]);
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_from_ndarray_too_large() {
+        let rows = i32::MAX as usize + 1;
+        let array = ndarray::ArrayView2::from_shape((rows, 0), &[]).unwrap();
+        assert_eq!(
+            Matrix::from_ndarray(array).err(),
+            Some(Error::Shape { rows, columns: 0 })
+        );
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn test_nalgebra() {
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+
+        let p = model.p_matrix_view();
+        assert_eq!(p.shape(), (2, 8));
+        assert_eq!(p[(1, 2)], model.p(1).unwrap()[2]);
+
+        let q = model.q_matrix_view();
+        assert_eq!(q.shape(), (2, 8));
+        assert_eq!(q[(1, 2)], model.q(1).unwrap()[2]);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn test_from_nalgebra() {
+        let mat = nalgebra::DMatrix::from_row_slice(2, 2, &[1.0, 2.0, f32::NAN, 3.0]);
+        let data = Matrix::from_nalgebra(&mat).unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 0, 1.0), (0, 1, 2.0), (1, 1, 3.0)]);
+
+        let data = Matrix::from_nalgebra(&mat.transpose()).unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 0, 1.0), (1, 0, 2.0), (1, 1, 3.0)]);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn test_from_nalgebra_too_large() {
+        let columns = i32::MAX as usize + 1;
+        let mat = nalgebra::DMatrix::<f32>::from_vec(0, columns, Vec::new());
+        assert_eq!(
+            Matrix::from_nalgebra(&mat).err(),
+            Some(Error::Shape { rows: 0, columns })
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use crate::bias::Biases;
+use crate::bindings::MfNode;
+use crate::{Error, Loss};
+
+/// How to check for divergence during or after training.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DivergenceCheck {
+    /// Do not check.
+    None,
+    /// Compare the training loss of the final model to the loss after the first iteration.
+    ///
+    /// LIBMF does not report progress during training, so this trains an extra
+    /// model with a single iteration.
+    AfterTraining,
+    /// Compare the training loss of each iteration to the loss of the first one, and
+    /// stop at the first iteration that diverges.
+    ///
+    /// This uses the loss the solver already computes, so it requires `Solver::RustSgd`
+    /// or `Solver::Als`.
+    PerIteration,
+}
+
+// the training loss of a diverged model is this many times the loss after one iteration
+const RATIO: f64 = 10.0;
+
+// avoids flagging tiny changes when the first iteration fits the data exactly
+const MIN_LOSS: f64 = 1e-6;
+
+pub(crate) fn diverged(loss: f64, first_loss: f64) -> bool {
+    !loss.is_finite() || loss > RATIO * first_loss.max(MIN_LOSS)
+}
+
+// checks the loss of each iteration for the Rust solvers
+pub(crate) struct Monitor {
+    enabled: bool,
+    first_loss: Option<f64>,
+}
+
+impl Monitor {
+    pub(crate) fn new(check: DivergenceCheck) -> Self {
+        Self {
+            enabled: check == DivergenceCheck::PerIteration,
+            first_loss: None,
+        }
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // iterations are zero-based, like the progress output, but reported from one
+    pub(crate) fn check(&mut self, iter: i32, loss: f64) -> Result<(), Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let first_loss = *self.first_loss.get_or_insert(if loss.is_finite() {
+            loss
+        } else {
+            f64::INFINITY
+        });
+        if diverged(loss, first_loss) {
+            return Err(Error::Diverged {
+                iteration: iter + 1,
+            });
+        }
+        Ok(())
+    }
+}
+
+// mean loss over the training set, using the raw dot products (before the link
+// function) so binary losses do not saturate
+pub(crate) fn training_loss(
+    p: &[f32],
+    q: &[f32],
+    factors: usize,
+    biases: &Biases,
+    data: &[MfNode],
+    loss: Loss,
+) -> f64 {
+    let mut sum = 0.0;
+    let mut count = 0;
+    for x in data {
+        // training sets can be larger than the model with OneClassL2
+        let (pu, qv) = match (row(p, factors, x.u), row(q, factors, x.v)) {
+            (Some(pu), Some(qv)) => (pu, qv),
+            _ => continue,
+        };
+        let z: f64 = pu
+            .iter()
+            .zip(qv)
+            .map(|(a, b)| *a as f64 * *b as f64)
+            .sum::<f64>()
+            + biases.predict(x.u, x.v) as f64;
+        sum += entry_loss(loss, x.r as f64, z);
+        count += 1;
+    }
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+fn row(factors: &[f32], k: usize, index: i32) -> Option<&[f32]> {
+    let start = index as usize * k;
+    factors.get(start..start + k)
+}
+
+fn entry_loss(loss: Loss, r: f64, z: f64) -> f64 {
+    match loss {
+        Loss::RealL1 => (r - z).abs(),
+        Loss::BinaryLog => {
+            // log(1 + exp(-rz)) without overflow
+            let m = -r * z;
+            m.max(0.0) + libm::log1p(libm::exp(-m.abs()))
+        }
+        Loss::BinaryL2 => {
+            let h = (1.0 - r * z).max(0.0);
+            h * h
+        }
+        Loss::BinaryL1 => (1.0 - r * z).max(0.0),
+        _ => (r - z) * (r - z),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diverged, training_loss, Monitor};
+    use crate::bias::Biases;
+    use crate::bindings::MfNode;
+    use crate::{DivergenceCheck, Error, Loss};
+    use alloc::vec;
+
+    #[test]
+    fn test_training_loss() {
+        let none = Biases::default();
+        let p = [1.0, 2.0, 0.5, 0.5];
+        let q = [1.0, 1.0, 2.0, 2.0];
+        let data = [
+            MfNode { u: 0, v: 0, r: 1.0 },
+            MfNode { u: 1, v: 1, r: 2.0 },
+            // outside the model
+            MfNode { u: 2, v: 0, r: 5.0 },
+        ];
+        assert_eq!(training_loss(&p, &q, 2, &none, &data, Loss::RealL2), 2.0);
+        assert_eq!(training_loss(&p, &q, 2, &none, &data, Loss::RealL1), 1.0);
+
+        let biases = Biases {
+            rows: vec![1.0],
+            columns: vec![0.0, -1.0],
+        };
+        assert_eq!(training_loss(&p, &q, 2, &biases, &data, Loss::RealL2), 5.0);
+
+        let data = [MfNode {
+            u: 0,
+            v: 0,
+            r: -1.0,
+        }];
+        assert_eq!(training_loss(&p, &q, 2, &none, &data, Loss::BinaryL1), 4.0);
+        assert_eq!(training_loss(&p, &q, 2, &none, &data, Loss::BinaryL2), 16.0);
+        let log = training_loss(&p, &q, 2, &none, &data, Loss::BinaryLog);
+        assert!((log - libm::log(1.0 + libm::exp(3.0))).abs() < 1e-12);
+
+        let p = [f32::INFINITY, 0.0];
+        assert!(!training_loss(&p, &q, 2, &none, &data, Loss::RealL2).is_finite());
+    }
+
+    #[test]
+    fn test_diverged() {
+        assert!(!diverged(1.0, 1.0));
+        assert!(!diverged(5.0, 1.0));
+        assert!(diverged(20.0, 1.0));
+        assert!(diverged(f64::NAN, 1.0));
+        assert!(diverged(f64::INFINITY, f64::INFINITY));
+        assert!(!diverged(1e-7, 0.0));
+    }
+
+    #[test]
+    fn test_monitor() {
+        let mut monitor = Monitor::new(DivergenceCheck::PerIteration);
+        assert_eq!(monitor.check(0, 2.0), Ok(()));
+        assert_eq!(monitor.check(1, 1.0), Ok(()));
+        assert_eq!(monitor.check(2, 19.0), Ok(()));
+        assert_eq!(
+            monitor.check(3, 21.0),
+            Err(Error::Diverged { iteration: 4 })
+        );
+
+        let mut monitor = Monitor::new(DivergenceCheck::PerIteration);
+        assert_eq!(
+            monitor.check(0, f64::NAN),
+            Err(Error::Diverged { iteration: 1 })
+        );
+
+        let mut monitor = Monitor::new(DivergenceCheck::AfterTraining);
+        assert_eq!(monitor.check(0, f64::NAN), Ok(()));
+    }
+}
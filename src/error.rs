@@ -1,24 +1,591 @@
+use crate::{LearningRateSchedule, Loss, Solver};
+use alloc::string::String;
+#[cfg(feature = "arrow")]
+use arrow_schema::ArrowError;
 use core::error;
 use core::fmt;
 
 /// An error.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
-    Io,
+    /// A file could not be read or written.
+    Io(IoError),
+    /// A node has an invalid row or column index.
     Node(usize),
-    Parameter(&'static str),
+    /// A parameter is invalid.
+    Parameter(ParameterError),
+    /// A model file could not be parsed.
+    MalformedModel {
+        /// The path of the file.
+        path: String,
+        /// The line number, if known.
+        line: Option<usize>,
+    },
+    /// An Arrow record batch could not be read or written.
+    #[cfg(feature = "arrow")]
+    Arrow(RecordBatchError),
+    /// A sparse matrix has an invalid structure.
+    Sparse(SparseError),
+    /// A dense matrix has more rows or columns than LIBMF supports.
+    Shape {
+        /// The number of rows.
+        rows: usize,
+        /// The number of columns.
+        columns: usize,
+    },
+    /// The training loss became non-finite or grew after the first iteration.
+    Diverged {
+        /// The iteration where divergence was detected, counting from one.
+        ///
+        /// This is the last iteration when checked after training.
+        iteration: i32,
+    },
+    /// LIBMF cross-validation failed with a non-zero status.
+    CrossValidation(i32),
+    /// LIBMF returned an error.
     Unknown,
 }
 
-impl error::Error for Error {}
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => err.source(),
+            Error::Sparse(err) => Some(err),
+            #[cfg(feature = "arrow")]
+            Error::Arrow(err) => err.source(),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Error::Io => f.write_str("cannot open file"),
+        match self {
+            Error::Io(err) => err.fmt(f),
             Error::Node(index) => write!(f, "invalid node at index {}", index),
-            Error::Parameter(err) => f.write_str(err),
+            Error::Parameter(err) => err.fmt(f),
+            Error::MalformedModel { path, line } => match line {
+                Some(line) => write!(f, "malformed model file: {} (line {})", path, line),
+                None => write!(f, "malformed model file: {}", path),
+            },
+            #[cfg(feature = "arrow")]
+            Error::Arrow(err) => err.fmt(f),
+            Error::Sparse(err) => write!(f, "invalid sparse matrix: {}", err),
+            Error::Shape { rows, columns } => write!(
+                f,
+                "matrix must have at most 2147483647 rows and columns (got {} x {})",
+                rows, columns
+            ),
+            Error::Diverged { iteration } => {
+                write!(f, "training diverged at iteration {}", iteration)
+            }
+            Error::CrossValidation(status) => {
+                write!(f, "cross-validation failed (status {})", status)
+            }
             Error::Unknown => f.write_str("unknown error"),
         }
     }
 }
+
+impl From<ParameterError> for Error {
+    fn from(err: ParameterError) -> Self {
+        Error::Parameter(err)
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<RecordBatchError> for Error {
+    fn from(err: RecordBatchError) -> Self {
+        Error::Arrow(err)
+    }
+}
+
+impl From<SparseError> for Error {
+    fn from(err: SparseError) -> Self {
+        Error::Sparse(err)
+    }
+}
+
+/// An I/O error.
+#[derive(Debug)]
+pub struct IoError {
+    path: String,
+    write: bool,
+    #[cfg(feature = "std")]
+    source: Option<std::io::Error>,
+}
+
+impl IoError {
+    pub(crate) fn read(path: &str) -> Self {
+        Self {
+            path: path.into(),
+            write: false,
+            #[cfg(feature = "std")]
+            source: None,
+        }
+    }
+
+    pub(crate) fn write(path: &str) -> Self {
+        Self {
+            write: true,
+            ..Self::read(path)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn with_source(mut self, source: std::io::Error) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns whether the file was being written (as opposed to read).
+    pub fn is_write(&self) -> bool {
+        self.write
+    }
+
+    /// Returns the error from the operating system, if known.
+    #[cfg(feature = "std")]
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        self.source.as_ref()
+    }
+}
+
+impl error::Error for IoError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        #[cfg(feature = "std")]
+        if let Some(err) = &self.source {
+            return Some(err);
+        }
+        None
+    }
+}
+
+// the reason is available from source
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.write {
+            write!(f, "cannot write file: {}", self.path)
+        } else {
+            write!(f, "cannot read file: {}", self.path)
+        }
+    }
+}
+
+// compare by path and kind since std::io::Error does not implement PartialEq
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        #[cfg(feature = "std")]
+        if self.source.as_ref().map(|e| e.kind()) != other.source.as_ref().map(|e| e.kind()) {
+            return false;
+        }
+        self.path == other.path && self.write == other.write
+    }
+}
+
+/// An invalid parameter.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ParameterError {
+    /// The training set or evaluation set is empty.
+    NoData,
+    /// There are more entries than LIBMF supports.
+    TooMuchData,
+    /// The number of latent factors is less than one.
+    Factors(i32),
+    /// The number of threads is less than one.
+    Threads(i32),
+    /// The number of bins is less than the number of threads.
+    Bins(i32),
+    /// The number of iterations is less than one.
+    Iterations(i32),
+    /// The L1-regularization parameter for P is negative.
+    LambdaP1(f32),
+    /// The L2-regularization parameter for P is negative.
+    LambdaP2(f32),
+    /// The L1-regularization parameter for Q is negative.
+    LambdaQ1(f32),
+    /// The L2-regularization parameter for Q is negative.
+    LambdaQ2(f32),
+    /// The learning rate is not positive.
+    LearningRate(f32),
+    /// The importance of negative entries is negative.
+    Alpha(f32),
+    /// The smoothing constant for confidence is not positive.
+    Eps(f32),
+    /// The number of folds is less than two or greater than the number of entries.
+    Folds(i32),
+    /// The loss function requires non-negative MF.
+    Nmf(Loss),
+    /// The loss function does not support non-negative MF.
+    NmfLoss(Loss),
+    /// The desired value of negative entries is not finite, or negative with non-negative MF.
+    C(f32),
+    /// The entry at the index has a value that is not finite.
+    Value(usize),
+    /// The entry at the index has a label other than -1 or 1 (for binary MF).
+    Label(usize),
+    /// The entry at the index has a negative value (for generalized KL-divergence).
+    NegativeValue(usize),
+    /// The evaluation set has rows not in the training set.
+    EvalRows(i32),
+    /// The evaluation set has columns not in the training set.
+    EvalColumns(i32),
+    /// The length of the latent factors is not a multiple of the number of factors.
+    FactorsLength(usize),
+    /// The loss function does not support row and column biases.
+    BiasLoss(Loss),
+    /// Row and column biases are not supported with cross-validation.
+    BiasCv,
+    /// The solver does not support row and column biases.
+    BiasSolver(Solver),
+    /// The L2-regularization parameter for the biases is negative.
+    LambdaBias(f32),
+    /// The solver does not support weights.
+    WeightSolver(Solver),
+    /// Weights are not supported with cross-validation.
+    WeightCv,
+    /// The entry at the index has a weight that is negative or not finite.
+    Weight(usize),
+    /// The loss function does not support side features.
+    FeatureLoss(Loss),
+    /// The feature at the index has a value that is not finite.
+    FeatureValue(usize),
+    /// The loss function is not supported by the solver.
+    SolverLoss(Solver, Loss),
+    /// L1-regularization is not supported by ALS.
+    AlsL1,
+    /// The solver is not supported with cross-validation.
+    SolverCv(Solver),
+    /// ALS is not supported with side features.
+    AlsFeatures,
+    /// The learning rate schedule has an invalid parameter.
+    LearningRateSchedule(LearningRateSchedule),
+    /// The solver does not support learning rate schedules.
+    ScheduleSolver(Solver),
+    /// The solver does not support checking for divergence after each iteration.
+    DivergenceSolver(Solver),
+    /// The BM25 term frequency saturation is negative or not finite.
+    K1(f32),
+    /// The BM25 length normalization is not between zero and one.
+    B(f32),
+}
+
+impl ParameterError {
+    /// Returns the name of the parameter, if the error is for a single parameter.
+    pub fn field(&self) -> Option<&'static str> {
+        let field = match self {
+            ParameterError::Factors(_) => "factors",
+            ParameterError::Threads(_) => "threads",
+            ParameterError::Bins(_) => "bins",
+            ParameterError::Iterations(_) => "iterations",
+            ParameterError::LambdaP1(_) => "lambda_p1",
+            ParameterError::LambdaP2(_) => "lambda_p2",
+            ParameterError::LambdaQ1(_) => "lambda_q1",
+            ParameterError::LambdaQ2(_) => "lambda_q2",
+            ParameterError::LambdaBias(_) => "lambda_bias",
+            ParameterError::LearningRate(_) => "learning_rate",
+            ParameterError::Alpha(_) => "alpha",
+            ParameterError::Eps(_) => "eps",
+            ParameterError::Folds(_) => "folds",
+            ParameterError::Nmf(_) => "nmf",
+            ParameterError::NmfLoss(_) => "nmf",
+            ParameterError::C(_) => "c",
+            ParameterError::LearningRateSchedule(_) => "learning_rate_schedule",
+            ParameterError::K1(_) => "k1",
+            ParameterError::B(_) => "b",
+            _ => return None,
+        };
+        Some(field)
+    }
+}
+
+impl error::Error for ParameterError {}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParameterError::NoData => f.write_str("no data"),
+            ParameterError::TooMuchData => f.write_str("too much data"),
+            ParameterError::Factors(v) => {
+                write!(f, "factors must be greater than zero (got {})", v)
+            }
+            ParameterError::Threads(v) => {
+                write!(f, "threads must be greater than zero (got {})", v)
+            }
+            ParameterError::Bins(v) => {
+                write!(f, "bins must be greater than threads (got {})", v)
+            }
+            ParameterError::Iterations(v) => {
+                write!(f, "iterations must be greater than zero (got {})", v)
+            }
+            ParameterError::LambdaP1(v)
+            | ParameterError::LambdaP2(v)
+            | ParameterError::LambdaQ1(v)
+            | ParameterError::LambdaQ2(v)
+            | ParameterError::LambdaBias(v) => {
+                write!(
+                    f,
+                    "{} must be non-negative (got {})",
+                    self.field().unwrap(),
+                    v
+                )
+            }
+            ParameterError::LearningRate(v) => {
+                write!(f, "learning_rate must be greater than zero (got {})", v)
+            }
+            ParameterError::Alpha(v) => write!(f, "alpha must be non-negative (got {})", v),
+            ParameterError::Eps(v) => write!(f, "eps must be greater than zero (got {})", v),
+            ParameterError::Folds(v) => write!(
+                f,
+                "folds must be at least two and at most the number of entries (got {})",
+                v
+            ),
+            ParameterError::Nmf(loss) => write!(f, "nmf must be set for {:?} loss", loss),
+            ParameterError::NmfLoss(loss) => write!(f, "nmf cannot be set for {:?} loss", loss),
+            ParameterError::C(v) => {
+                write!(f, "c must be finite, and non-negative with nmf (got {})", v)
+            }
+            ParameterError::Value(index) => write!(f, "value must be finite at index {}", index),
+            ParameterError::Label(index) => {
+                write!(f, "label must be -1 or 1 at index {}", index)
+            }
+            ParameterError::NegativeValue(index) => {
+                write!(f, "value must be non-negative at index {}", index)
+            }
+            ParameterError::EvalRows(v) => write!(
+                f,
+                "eval set cannot have extra rows for OneClassL2 loss (got {} rows)",
+                v
+            ),
+            ParameterError::EvalColumns(v) => write!(
+                f,
+                "eval set cannot have extra columns for OneClassL2 loss (got {} columns)",
+                v
+            ),
+            ParameterError::FactorsLength(v) => write!(
+                f,
+                "length of latent factors must be a multiple of factors (got {})",
+                v
+            ),
+            ParameterError::BiasLoss(loss) => {
+                write!(
+                    f,
+                    "row and column biases are not supported for {:?} loss",
+                    loss
+                )
+            }
+            ParameterError::BiasCv => {
+                f.write_str("row and column biases are not supported with cross-validation")
+            }
+            ParameterError::BiasSolver(solver) => {
+                write!(
+                    f,
+                    "row and column biases are not supported by the {:?} solver",
+                    solver
+                )
+            }
+            ParameterError::WeightSolver(solver) => {
+                write!(f, "weights are not supported by the {:?} solver", solver)
+            }
+            ParameterError::WeightCv => {
+                f.write_str("weights are not supported with cross-validation")
+            }
+            ParameterError::Weight(index) => {
+                write!(
+                    f,
+                    "weight must be finite and non-negative at index {}",
+                    index
+                )
+            }
+            ParameterError::FeatureLoss(loss) => {
+                write!(f, "side features are not supported for {:?} loss", loss)
+            }
+            ParameterError::FeatureValue(index) => {
+                write!(f, "feature value must be finite at index {}", index)
+            }
+            ParameterError::SolverLoss(solver, loss) => {
+                write!(
+                    f,
+                    "the {:?} solver is not supported for {:?} loss",
+                    solver, loss
+                )
+            }
+            ParameterError::AlsL1 => f.write_str("ALS does not support L1-regularization"),
+            ParameterError::SolverCv(solver) => write!(
+                f,
+                "the {:?} solver is not supported with cross-validation",
+                solver
+            ),
+            ParameterError::AlsFeatures => f.write_str("ALS is not supported with side features"),
+            ParameterError::LearningRateSchedule(v) => {
+                write!(
+                    f,
+                    "learning_rate_schedule has an invalid parameter (got {:?})",
+                    v
+                )
+            }
+            ParameterError::ScheduleSolver(solver) => write!(
+                f,
+                "learning rate schedules are not supported by the {:?} solver",
+                solver
+            ),
+            ParameterError::DivergenceSolver(solver) => write!(
+                f,
+                "per-iteration divergence checks are not supported by the {:?} solver",
+                solver
+            ),
+            ParameterError::K1(v) => write!(f, "k1 must be finite and non-negative (got {})", v),
+            ParameterError::B(v) => write!(f, "b must be between 0 and 1 (got {})", v),
+        }
+    }
+}
+
+/// An invalid sparse matrix structure.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum SparseError {
+    /// The lengths of the indices and data are different.
+    Length {
+        /// The length of the indices.
+        indices: usize,
+        /// The length of the data.
+        data: usize,
+    },
+    /// The index pointer does not start with zero.
+    IndptrStart,
+    /// The index pointer does not end with the number of entries.
+    IndptrEnd,
+    /// The index pointer decreases at the position.
+    IndptrOrder(usize),
+    /// There are more rows or columns than LIBMF supports.
+    TooManyRowsOrColumns(usize),
+    /// The index is too large for LIBMF.
+    IndexOutOfRange(usize),
+}
+
+impl error::Error for SparseError {}
+
+impl fmt::Display for SparseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SparseError::Length { indices, data } => write!(
+                f,
+                "indices and data must have the same length (got {} and {})",
+                indices, data
+            ),
+            SparseError::IndptrStart => f.write_str("indptr must start with zero"),
+            SparseError::IndptrEnd => f.write_str("indptr must end with the number of entries"),
+            SparseError::IndptrOrder(i) => {
+                write!(f, "indptr must be non-decreasing (at position {})", i)
+            }
+            SparseError::TooManyRowsOrColumns(v) => {
+                write!(f, "too many rows or columns (got {})", v)
+            }
+            SparseError::IndexOutOfRange(v) => write!(f, "index out of range (got {})", v),
+        }
+    }
+}
+
+/// An Arrow record batch that could not be read or written.
+#[cfg(feature = "arrow")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RecordBatchError {
+    /// The column is not in the record batch.
+    MissingColumn(String),
+    /// The column has a null value.
+    Null {
+        /// The name of the column.
+        column: String,
+        /// The row with the null value (counting across record batches).
+        row: usize,
+    },
+    /// The row or column index is a float that is not an integer in range.
+    Index {
+        /// The name of the column.
+        column: String,
+        /// The row with the invalid index (counting across record batches).
+        row: usize,
+    },
+    /// The column could not be cast to the required type.
+    Cast {
+        /// The name of the column.
+        column: String,
+        /// The error from Arrow.
+        source: ArrowError,
+    },
+    /// Arrow returned an error.
+    Arrow(ArrowError),
+}
+
+#[cfg(feature = "arrow")]
+impl error::Error for RecordBatchError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RecordBatchError::Cast { source, .. } => Some(source),
+            RecordBatchError::Arrow(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// the reason is available from source
+#[cfg(feature = "arrow")]
+impl fmt::Display for RecordBatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordBatchError::MissingColumn(column) => write!(f, "column not found: {}", column),
+            RecordBatchError::Null { column, row } => {
+                write!(f, "column {} has a null at row {}", column, row)
+            }
+            RecordBatchError::Index { column, row } => {
+                write!(f, "column {} has an invalid index at row {}", column, row)
+            }
+            RecordBatchError::Cast { column, .. } => write!(f, "cannot cast column {}", column),
+            RecordBatchError::Arrow(_) => f.write_str("arrow error"),
+        }
+    }
+}
+
+// compare Arrow errors by message since ArrowError does not implement PartialEq
+#[cfg(feature = "arrow")]
+impl PartialEq for RecordBatchError {
+    fn eq(&self, other: &Self) -> bool {
+        use alloc::string::ToString;
+
+        match (self, other) {
+            (RecordBatchError::MissingColumn(a), RecordBatchError::MissingColumn(b)) => a == b,
+            (
+                RecordBatchError::Null { column, row },
+                RecordBatchError::Null {
+                    column: other_column,
+                    row: other_row,
+                },
+            ) => column == other_column && row == other_row,
+            (
+                RecordBatchError::Index { column, row },
+                RecordBatchError::Index {
+                    column: other_column,
+                    row: other_row,
+                },
+            ) => column == other_column && row == other_row,
+            (
+                RecordBatchError::Cast { column, source },
+                RecordBatchError::Cast {
+                    column: other_column,
+                    source: other_source,
+                },
+            ) => column == other_column && source.to_string() == other_source.to_string(),
+            (RecordBatchError::Arrow(a), RecordBatchError::Arrow(b)) => {
+                a.to_string() == b.to_string()
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,547 @@
+use crate::bias;
+use crate::bindings::{MfNode, MfProblem};
+use crate::metrics::{EntryError, ErrorSum};
+use crate::{Error, EvaluationReport, Loss, Matrix, ParameterError};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Calculates metrics in Rust from latent factors.
+pub struct Evaluator<'a> {
+    p: &'a [f32],
+    q: &'a [f32],
+    factors: usize,
+    bias: f32,
+    loss: Loss,
+    row_bias: &'a [f32],
+    column_bias: &'a [f32],
+    #[cfg(feature = "std")]
+    threads: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    /// Creates a new evaluator.
+    ///
+    /// For binary losses, predictions are the sign of the dot product, like LIBMF.
+    pub fn new(
+        p_factors: &'a [f32],
+        q_factors: &'a [f32],
+        factors: i32,
+        bias: f32,
+        loss: Loss,
+    ) -> Result<Self, Error> {
+        if factors < 1 {
+            return Err(ParameterError::Factors(factors).into());
+        }
+
+        let factors = factors as usize;
+        if p_factors.len() % factors != 0 {
+            return Err(ParameterError::FactorsLength(p_factors.len()).into());
+        }
+
+        if q_factors.len() % factors != 0 {
+            return Err(ParameterError::FactorsLength(q_factors.len()).into());
+        }
+
+        Ok(Self {
+            p: p_factors,
+            q: q_factors,
+            factors,
+            bias,
+            loss,
+            row_bias: &[],
+            column_bias: &[],
+            #[cfg(feature = "std")]
+            threads: 1,
+        })
+    }
+
+    /// Sets the bias terms for rows and columns (either can be empty).
+    ///
+    /// Rows and columns past the end have a bias of zero.
+    pub fn biases(&mut self, row_bias: &'a [f32], column_bias: &'a [f32]) -> &mut Self {
+        self.row_bias = row_bias;
+        self.column_bias = column_bias;
+        self
+    }
+
+    /// Sets the number of threads.
+    #[cfg(feature = "std")]
+    pub fn threads(&mut self, value: i32) -> &mut Self {
+        self.threads = value.max(1) as usize;
+        self
+    }
+
+    /// Returns the predicted value for a row and column.
+    pub fn predict(&self, row_index: i32, column_index: i32) -> f32 {
+        let prediction = self.predict_factors(row_index, column_index);
+        if self.row_bias.is_empty() && self.column_bias.is_empty() {
+            return prediction;
+        }
+        prediction + bias::get(self.row_bias, row_index) + bias::get(self.column_bias, column_index)
+    }
+
+    fn predict_factors(&self, row_index: i32, column_index: i32) -> f32 {
+        if row_index < 0 || column_index < 0 {
+            return link(self.loss, self.bias);
+        }
+
+        let start = row_index as usize * self.factors;
+        let p = match self.p.get(start..start + self.factors) {
+            Some(v) => v,
+            None => return link(self.loss, self.bias),
+        };
+
+        let start = column_index as usize * self.factors;
+        let q = match self.q.get(start..start + self.factors) {
+            Some(v) => v,
+            None => return link(self.loss, self.bias),
+        };
+
+        // same order of operations as LIBMF
+        let z = p.iter().zip(q).fold(0.0, |acc, (a, b)| acc + a * b);
+        link(self.loss, if z.is_nan() { self.bias } else { z })
+    }
+
+    /// Calculates RMSE (for real-valued MF).
+    pub fn rmse(&self, data: &Matrix) -> Result<f64, Error> {
+        Ok(self.error_sum(data)?.rmse())
+    }
+
+    /// Calculates MAE (for real-valued MF).
+    pub fn mae(&self, data: &Matrix) -> Result<f64, Error> {
+        Ok(self.error_sum(data)?.mae())
+    }
+
+    /// Calculates generalized KL-divergence (for non-negative real-valued MF).
+    pub fn gkl(&self, data: &Matrix) -> Result<f64, Error> {
+        Ok(self.error_sum(data)?.gkl())
+    }
+
+    /// Calculates logarithmic loss (for binary MF).
+    pub fn logloss(&self, data: &Matrix) -> Result<f64, Error> {
+        Ok(self.error_sum(data)?.logloss())
+    }
+
+    /// Calculates accuracy (for binary MF).
+    pub fn accuracy(&self, data: &Matrix) -> Result<f64, Error> {
+        Ok(self.error_sum(data)?.accuracy())
+    }
+
+    /// Calculates MPR (for one-class MF).
+    pub fn mpr(&self, data: &Matrix, transpose: bool) -> Result<f64, Error> {
+        Ok(self.rank_sum(data, transpose)?.mpr())
+    }
+
+    /// Calculates AUC (for one-class MF).
+    pub fn auc(&self, data: &Matrix, transpose: bool) -> Result<f64, Error> {
+        Ok(self.rank_sum(data, transpose)?.auc())
+    }
+
+    /// Calculates the metrics relevant to the loss function.
+    pub fn evaluate(&self, data: &Matrix) -> Result<EvaluationReport, Error> {
+        let loss = self.loss;
+        let mut report = EvaluationReport::new(loss, data.data.len());
+        match loss {
+            Loss::RealL2 | Loss::RealL1 | Loss::RealKL => {
+                let sum = self.error_sum(data)?;
+                report.rmse = Some(sum.rmse());
+                report.mae = Some(sum.mae());
+                if loss == Loss::RealKL {
+                    report.gkl = Some(sum.gkl());
+                }
+            }
+            Loss::BinaryLog | Loss::BinaryL2 | Loss::BinaryL1 => {
+                let sum = self.error_sum(data)?;
+                report.logloss = Some(sum.logloss());
+                report.accuracy = Some(sum.accuracy());
+            }
+            Loss::OneClassRow | Loss::OneClassCol | Loss::OneClassL2 => {
+                let sum = self.rank_sum(data, loss == Loss::OneClassCol)?;
+                report.mpr = Some(sum.mpr());
+                report.auc = Some(sum.auc());
+            }
+        }
+        Ok(report)
+    }
+
+    fn error_sum(&self, data: &Matrix) -> Result<ErrorSum, Error> {
+        // check for invalid nodes
+        let _: MfProblem = data.try_into()?;
+
+        let sum_chunk = |nodes: &[MfNode]| {
+            let mut sum = ErrorSum::default();
+            for x in nodes {
+                sum.add(&EntryError::new(x.r, self.predict(x.u, x.v)));
+            }
+            sum
+        };
+
+        Ok(self.map_chunks(&data.data, sum_chunk).into_iter().fold(
+            ErrorSum::default(),
+            |mut acc, x| {
+                acc.merge(&x);
+                acc
+            },
+        ))
+    }
+
+    fn rank_sum(&self, data: &Matrix, transpose: bool) -> Result<RankSum, Error> {
+        let prob: MfProblem = data.try_into()?;
+
+        let m = (self.p.len() / self.factors).max(prob.m as usize);
+        let n = (self.q.len() / self.factors).max(prob.n as usize);
+
+        // (row, column) pairs of positive entries, sorted and deduplicated
+        let mut pos: Vec<(i32, i32)> = data
+            .data
+            .iter()
+            .filter(|x| x.r > 0.0)
+            .map(|x| if transpose { (x.v, x.u) } else { (x.u, x.v) })
+            .collect();
+        pos.sort_unstable();
+        pos.dedup();
+
+        let candidates = if transpose { m } else { n };
+
+        let mut groups = Vec::new();
+        let mut start = 0;
+        while start < pos.len() {
+            let end = start + pos[start..].partition_point(|x| x.0 == pos[start].0);
+            groups.push(&pos[start..end]);
+            start = end;
+        }
+
+        let sum_chunk = |groups: &[&[(i32, i32)]]| {
+            let mut sum = RankSum::default();
+            let mut scores = vec![0.0; candidates];
+            let mut positive = vec![false; candidates];
+            for group in groups {
+                let i = group[0].0;
+                for (j, score) in scores.iter_mut().enumerate() {
+                    *score = if transpose {
+                        self.predict(j as i32, i)
+                    } else {
+                        self.predict(i, j as i32)
+                    };
+                }
+                for x in group.iter() {
+                    positive[x.1 as usize] = true;
+                }
+                sum.add(&scores, &positive, group);
+                for x in group.iter() {
+                    positive[x.1 as usize] = false;
+                }
+            }
+            sum
+        };
+
+        Ok(self.map_chunks(&groups, sum_chunk).into_iter().fold(
+            RankSum::default(),
+            |mut acc, x| {
+                acc.merge(&x);
+                acc
+            },
+        ))
+    }
+
+    #[cfg(feature = "std")]
+    fn map_chunks<T: Sync, R: Send, F: Fn(&[T]) -> R + Sync>(&self, items: &[T], f: F) -> Vec<R> {
+        if self.threads < 2 || items.len() < 2 {
+            return vec![f(items)];
+        }
+
+        let chunk_size = (items.len() + self.threads - 1) / self.threads;
+        std::thread::scope(|s| {
+            let handles: Vec<_> = items
+                .chunks(chunk_size)
+                .map(|chunk| s.spawn(|| f(chunk)))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        })
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn map_chunks<T, R, F: Fn(&[T]) -> R>(&self, items: &[T], f: F) -> Vec<R> {
+        vec![f(items)]
+    }
+}
+
+// same as mf_predict
+pub(crate) fn link(loss: Loss, z: f32) -> f32 {
+    match loss {
+        Loss::BinaryLog | Loss::BinaryL2 | Loss::BinaryL1 => {
+            if z > 0.0 {
+                1.0
+            } else {
+                -1.0
+            }
+        }
+        _ => z,
+    }
+}
+
+#[derive(Default)]
+struct RankSum {
+    count: usize,
+    mpr: f64,
+    auc: f64,
+}
+
+impl RankSum {
+    // ranks positive entries against negative entries for a single row
+    fn add(&mut self, scores: &[f32], positive: &[bool], group: &[(i32, i32)]) {
+        let mut neg_scores: Vec<f32> = scores
+            .iter()
+            .zip(positive)
+            .filter(|(_, &p)| !p)
+            .map(|(&s, _)| s)
+            .collect();
+
+        // rows without negative entries are skipped
+        if neg_scores.is_empty() {
+            return;
+        }
+        neg_scores.sort_unstable_by(|a, b| a.total_cmp(b));
+
+        let neg_len = neg_scores.len() as f64;
+        let mut mpr = 0.0;
+        let mut auc = 0.0;
+        for x in group {
+            let score = scores[x.1 as usize];
+            let below = neg_scores.partition_point(|&s| s < score);
+            let not_above = neg_scores.partition_point(|&s| s <= score);
+            mpr += (neg_scores.len() - not_above) as f64 / neg_len;
+            auc += below as f64 / neg_len;
+        }
+
+        let pos_len = group.len() as f64;
+        self.count += 1;
+        self.mpr += mpr / pos_len;
+        self.auc += auc / pos_len;
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.mpr += other.mpr;
+        self.auc += other.auc;
+    }
+
+    fn mpr(&self) -> f64 {
+        self.mpr / self.count as f64
+    }
+
+    fn auc(&self) -> f64 {
+        self.auc / self.count as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Evaluator, Loss, Matrix, Model};
+
+    fn generate_data() -> Matrix {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 0, 2.0);
+        data.push(1, 1, 1.0);
+        data
+    }
+
+    fn assert_in_delta(expected: f64, actual: f64) {
+        assert!(
+            (expected - actual).abs() < 1e-6,
+            "expected {} to be close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_metrics() {
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+        let evaluator = model.evaluator().unwrap();
+
+        assert_eq!(model.predict(0, 1), evaluator.predict(0, 1));
+        assert_eq!(model.predict(1000, 1000), evaluator.predict(1000, 1000));
+        assert_in_delta(model.rmse(&data).unwrap(), evaluator.rmse(&data).unwrap());
+        assert_in_delta(model.mae(&data).unwrap(), evaluator.mae(&data).unwrap());
+        assert_in_delta(model.gkl(&data).unwrap(), evaluator.gkl(&data).unwrap());
+        assert_in_delta(
+            model.logloss(&data).unwrap(),
+            evaluator.logloss(&data).unwrap(),
+        );
+        assert_in_delta(
+            model.accuracy(&data).unwrap(),
+            evaluator.accuracy(&data).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_metrics_binary() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 0, -1.0);
+        data.push(1, 1, 1.0);
+        let model = Model::params()
+            .loss(Loss::BinaryLog)
+            .quiet(true)
+            .fit(&data)
+            .unwrap();
+        let evaluator = model.evaluator().unwrap();
+
+        assert_eq!(model.predict(0, 1), evaluator.predict(0, 1));
+        assert_eq!(model.predict(1000, 1000), evaluator.predict(1000, 1000));
+        assert_in_delta(
+            model.logloss(&data).unwrap(),
+            evaluator.logloss(&data).unwrap(),
+        );
+        assert_in_delta(
+            model.accuracy(&data).unwrap(),
+            evaluator.accuracy(&data).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_metrics_one_class() {
+        let mut data = generate_data();
+        data.push(2, 2, 1.0);
+        let model = Model::params()
+            .loss(Loss::OneClassRow)
+            .quiet(true)
+            .fit(&data)
+            .unwrap();
+        let evaluator = model.evaluator().unwrap();
+
+        for transpose in [false, true] {
+            assert_in_delta(
+                model.mpr(&data, transpose).unwrap(),
+                evaluator.mpr(&data, transpose).unwrap(),
+            );
+            assert_in_delta(
+                model.auc(&data, transpose).unwrap(),
+                evaluator.auc(&data, transpose).unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_metrics_empty() {
+        let evaluator = Evaluator::new(&[1.0], &[1.0], 1, 0.0, Loss::RealL2).unwrap();
+
+        let empty = Matrix::new();
+        assert_eq!(0.0, evaluator.rmse(&empty).unwrap());
+        assert_eq!(0.0, evaluator.mae(&empty).unwrap());
+        assert_eq!(0.0, evaluator.gkl(&empty).unwrap());
+        assert_eq!(0.0, evaluator.logloss(&empty).unwrap());
+        assert_eq!(0.0, evaluator.accuracy(&empty).unwrap());
+        assert!(evaluator.mpr(&empty, false).unwrap().is_nan());
+        assert!(evaluator.auc(&empty, false).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_imported_factors() {
+        let p = [1.0, 0.0, 0.0, 1.0];
+        let q = [1.0, 0.0, 0.0, 1.0, 0.5, 0.5];
+        let evaluator = Evaluator::new(&p, &q, 2, 0.25, Loss::RealL2).unwrap();
+
+        assert_eq!(1.0, evaluator.predict(0, 0));
+        assert_eq!(0.0, evaluator.predict(0, 1));
+        assert_eq!(0.5, evaluator.predict(1, 2));
+        assert_eq!(0.25, evaluator.predict(2, 0));
+        assert_eq!(0.25, evaluator.predict(-1, 0));
+
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 1, 1.0);
+        assert_eq!(0.0, evaluator.rmse(&data).unwrap());
+        assert_eq!(0.0, evaluator.mpr(&data, false).unwrap());
+        assert_eq!(1.0, evaluator.auc(&data, false).unwrap());
+
+        let mut data = Matrix::new();
+        data.push(0, 1, 1.0);
+        assert_eq!(1.0, evaluator.mpr(&data, false).unwrap());
+        assert_eq!(0.0, evaluator.auc(&data, false).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let p = [1.0, 0.0, 0.0, 1.0];
+        let q = [1.0, 0.0, 0.0, 1.0];
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 1, 3.0);
+
+        let evaluator = Evaluator::new(&p, &q, 2, 0.0, Loss::RealKL).unwrap();
+        let report = evaluator.evaluate(&data).unwrap();
+        assert_eq!(report.count, 2);
+        assert_eq!(report.rmse, Some(evaluator.rmse(&data).unwrap()));
+        assert_eq!(report.mae, Some(1.0));
+        assert_eq!(report.gkl, Some(evaluator.gkl(&data).unwrap()));
+        assert_eq!(report.logloss, None);
+
+        let evaluator = Evaluator::new(&p, &q, 2, 0.0, Loss::BinaryLog).unwrap();
+        let report = evaluator.evaluate(&data).unwrap();
+        assert_eq!(report.rmse, None);
+        assert_eq!(report.accuracy, Some(1.0));
+
+        let evaluator = Evaluator::new(&p, &q, 2, 0.0, Loss::OneClassCol).unwrap();
+        let report = evaluator.evaluate(&data).unwrap();
+        assert_eq!(report.mpr, Some(evaluator.mpr(&data, true).unwrap()));
+        assert_eq!(report.auc, Some(1.0));
+    }
+
+    #[test]
+    fn test_binary() {
+        let p = [2.0, 0.0, 0.0, -0.5];
+        let q = [1.0, 0.0, 0.0, 1.0];
+
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 1, -1.0);
+
+        for loss in [Loss::BinaryLog, Loss::BinaryL2, Loss::BinaryL1] {
+            let evaluator = Evaluator::new(&p, &q, 2, 0.25, loss).unwrap();
+            assert_eq!(1.0, evaluator.predict(0, 0));
+            assert_eq!(-1.0, evaluator.predict(1, 1));
+            assert_eq!(-1.0, evaluator.predict(0, 1));
+            assert_eq!(1.0, evaluator.predict(2, 0));
+            assert_eq!(0.0, evaluator.rmse(&data).unwrap());
+            assert_eq!(1.0, evaluator.accuracy(&data).unwrap());
+            assert_in_delta(
+                libm::log(1.0 + libm::exp(-1.0)),
+                evaluator.logloss(&data).unwrap(),
+            );
+        }
+
+        let evaluator = Evaluator::new(&p, &q, 2, 0.25, Loss::RealL2).unwrap();
+        assert_eq!(2.0, evaluator.predict(0, 0));
+    }
+
+    #[test]
+    fn test_bad_factors() {
+        assert!(Evaluator::new(&[1.0], &[1.0], 0, 0.0, Loss::RealL2).is_err());
+        assert!(Evaluator::new(&[1.0, 2.0, 3.0], &[1.0, 2.0], 2, 0.0, Loss::RealL2).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_threads() {
+        let p = [1.0, 0.0, 0.0, 1.0, 0.5, 0.5];
+        let q = [1.0, 0.0, 0.0, 1.0, 0.5, 0.5];
+        let mut evaluator = Evaluator::new(&p, &q, 2, 0.0, Loss::RealL2).unwrap();
+
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 2, 1.0);
+        data.push(2, 1, 0.5);
+        let rmse = evaluator.rmse(&data).unwrap();
+        let mpr = evaluator.mpr(&data, false).unwrap();
+        let auc = evaluator.auc(&data, true).unwrap();
+
+        evaluator.threads(3);
+        assert_eq!(rmse, evaluator.rmse(&data).unwrap());
+        assert_eq!(mpr, evaluator.mpr(&data, false).unwrap());
+        assert_eq!(auc, evaluator.auc(&data, true).unwrap());
+    }
+}
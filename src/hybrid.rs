@@ -0,0 +1,596 @@
+use crate::bias::{self, weighted_mean, Biases};
+use crate::bindings::MfParameter;
+use crate::divergence::Monitor;
+use crate::evaluator::link;
+use crate::sgd::{dot, gradient, soft_threshold, Rng, SEED};
+use crate::{Error, EvaluationReport, Evaluator, Loss, Matrix, ModelMetadata, Params};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A model where the latent factors of rows and columns are sums of feature embeddings.
+///
+/// Rows and columns in the training set have their own embedding in addition to
+/// the embeddings of their features, so new rows and columns can be predicted
+/// from their features alone.
+#[derive(Debug)]
+pub struct HybridModel {
+    pub(crate) loss: Loss,
+    pub(crate) factors: usize,
+    pub(crate) bias: f32,
+    // NaN for rows and columns without entries or known features
+    pub(crate) p: Vec<f32>,
+    pub(crate) q: Vec<f32>,
+    // NaN for features that were not in the training set
+    pub(crate) row_embeddings: Vec<f32>,
+    pub(crate) column_embeddings: Vec<f32>,
+    pub(crate) biases: Biases,
+    pub(crate) metadata: ModelMetadata,
+}
+
+impl HybridModel {
+    /// Returns the predicted value for a row and column.
+    pub fn predict(&self, row_index: i32, column_index: i32) -> f32 {
+        self.combine(self.p(row_index), self.q(column_index))
+            + self.biases.predict(row_index, column_index)
+    }
+
+    /// Returns the predicted value for a row and a new column described by its features.
+    ///
+    /// Features are pairs of feature index and value. Features that were not in the
+    /// training set are ignored.
+    pub fn predict_new_column(&self, row_index: i32, features: &[(i32, f32)]) -> f32 {
+        let q = self.q_from_features(features);
+        self.combine(self.p(row_index), q.as_deref()) + self.biases.row(row_index)
+    }
+
+    /// Returns the predicted value for a new row described by its features and a column.
+    ///
+    /// Features are pairs of feature index and value. Features that were not in the
+    /// training set are ignored.
+    pub fn predict_new_row(&self, features: &[(i32, f32)], column_index: i32) -> f32 {
+        let p = self.p_from_features(features);
+        self.combine(p.as_deref(), self.q(column_index)) + self.biases.column(column_index)
+    }
+
+    fn combine(&self, p: Option<&[f32]>, q: Option<&[f32]>) -> f32 {
+        let z = match (p, q) {
+            (Some(p), Some(q)) => dot(p, q),
+            _ => self.bias,
+        };
+        link(self.loss, if z.is_nan() { self.bias } else { z })
+    }
+
+    /// Returns the latent factors for a new row, or `None` if none of the features were in the training set.
+    pub fn p_from_features(&self, features: &[(i32, f32)]) -> Option<Vec<f32>> {
+        embed(&self.row_embeddings, self.factors, features)
+    }
+
+    /// Returns the latent factors for a new column, or `None` if none of the features were in the training set.
+    pub fn q_from_features(&self, features: &[(i32, f32)]) -> Option<Vec<f32>> {
+        embed(&self.column_embeddings, self.factors, features)
+    }
+
+    /// Returns information about how the model was trained.
+    pub fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    /// Returns the loss function.
+    pub fn loss(&self) -> Loss {
+        self.loss
+    }
+
+    /// Returns the number of rows.
+    pub fn rows(&self) -> i32 {
+        (self.p.len() / self.factors) as i32
+    }
+
+    /// Returns the number of columns.
+    pub fn columns(&self) -> i32 {
+        (self.q.len() / self.factors) as i32
+    }
+
+    /// Returns the number of factors.
+    pub fn factors(&self) -> i32 {
+        self.factors as i32
+    }
+
+    /// Returns the bias.
+    pub fn bias(&self) -> f32 {
+        self.bias
+    }
+
+    /// Returns the bias term for a row, if row biases were learned.
+    pub fn row_bias(&self, row_index: i32) -> Option<f32> {
+        if self.biases.rows.is_empty() {
+            return None;
+        }
+        Some(self.biases.row(row_index))
+    }
+
+    /// Returns the bias term for a column, if column biases were learned.
+    pub fn column_bias(&self, column_index: i32) -> Option<f32> {
+        if self.biases.columns.is_empty() {
+            return None;
+        }
+        Some(self.biases.column(column_index))
+    }
+
+    /// Returns the latent factors for rows.
+    pub fn p_factors(&self) -> &[f32] {
+        &self.p
+    }
+
+    /// Returns the latent factors for columns.
+    pub fn q_factors(&self) -> &[f32] {
+        &self.q
+    }
+
+    /// Returns the latent factors for a specific row.
+    pub fn p(&self, row_index: i32) -> Option<&[f32]> {
+        factors(&self.p, self.factors, row_index)
+    }
+
+    /// Returns the latent factors for a specific column.
+    pub fn q(&self, column_index: i32) -> Option<&[f32]> {
+        factors(&self.q, self.factors, column_index)
+    }
+
+    /// Returns an evaluator that calculates metrics in Rust.
+    pub fn evaluator(&self) -> Result<Evaluator<'_>, Error> {
+        let mut evaluator = Evaluator::new(&self.p, &self.q, self.factors(), self.bias, self.loss)?;
+        evaluator.biases(&self.biases.rows, &self.biases.columns);
+        Ok(evaluator)
+    }
+
+    /// Calculates the metrics relevant to the loss function.
+    pub fn evaluate(&self, data: &Matrix) -> Result<EvaluationReport, Error> {
+        self.evaluator()?.evaluate(data)
+    }
+}
+
+fn factors(values: &[f32], k: usize, index: i32) -> Option<&[f32]> {
+    if index < 0 {
+        return None;
+    }
+    let start = index as usize * k;
+    values.get(start..start + k)
+}
+
+fn embed(embeddings: &[f32], k: usize, features: &[(i32, f32)]) -> Option<Vec<f32>> {
+    let mut sum = vec![0.0; k];
+    let mut known = false;
+    for &(index, value) in features {
+        match factors(embeddings, k, index) {
+            Some(e) if !e[0].is_nan() => {
+                for (s, e) in sum.iter_mut().zip(e) {
+                    *s += value * e;
+                }
+                known = true;
+            }
+            _ => {}
+        }
+    }
+    if known {
+        Some(sum)
+    } else {
+        None
+    }
+}
+
+// the embeddings that are summed for each row or column
+struct Side {
+    k: usize,
+    eta: f32,
+    lambda1: f32,
+    lambda2: f32,
+    nmf: bool,
+    // rows and columns in the training set have an identity embedding,
+    // which comes before the feature embeddings
+    identities: usize,
+    starts: Vec<usize>,
+    items: Vec<(usize, f32)>,
+    embeddings: Vec<f32>,
+    gradients: Vec<f32>,
+    trained: Vec<bool>,
+}
+
+impl Side {
+    fn new(
+        identities: usize,
+        features: &Matrix,
+        (lambda1, lambda2): (f32, f32),
+        param: &MfParameter,
+        rng: &mut Rng,
+    ) -> Self {
+        let k = param.k as usize;
+        let len = identities.max(features.rows() as usize);
+        let mut counts = vec![0; len + 1];
+        for i in 0..identities {
+            counts[i + 1] += 1;
+        }
+        for x in &features.data {
+            counts[x.u as usize + 1] += 1;
+        }
+        for i in 0..len {
+            counts[i + 1] += counts[i];
+        }
+
+        let starts = counts.clone();
+        let mut items = vec![(0, 0.0); counts[len]];
+        for i in 0..identities {
+            items[counts[i]] = (i, 1.0);
+            counts[i] += 1;
+        }
+        for x in &features.data {
+            let u = x.u as usize;
+            items[counts[u]] = (identities + x.v as usize, x.r);
+            counts[u] += 1;
+        }
+
+        let total = identities + features.columns() as usize;
+        let scale = libm::sqrtf(1.0 / k as f32);
+        Self {
+            k,
+            eta: param.eta,
+            lambda1,
+            lambda2,
+            nmf: param.do_nmf,
+            identities,
+            starts,
+            items,
+            embeddings: (0..total * k).map(|_| rng.next_f32() * scale).collect(),
+            gradients: vec![1.0; total],
+            trained: vec![false; total],
+        }
+    }
+
+    fn items(&self, index: usize) -> &[(usize, f32)] {
+        &self.items[self.starts[index]..self.starts[index + 1]]
+    }
+
+    fn sum(&self, index: usize, out: &mut [f32]) {
+        out.fill(0.0);
+        for &(i, value) in self.items(index) {
+            let e = &self.embeddings[i * self.k..(i + 1) * self.k];
+            for (o, e) in out.iter_mut().zip(e) {
+                *o += value * e;
+            }
+        }
+    }
+
+    // `g` is the negative gradient with respect to the sum of the embeddings
+    fn update(&mut self, index: usize, g: &[f32], w: f32) {
+        let k = self.k;
+        for j in self.starts[index]..self.starts[index + 1] {
+            let (i, value) = self.items[j];
+            let e = &mut self.embeddings[i * k..(i + 1) * k];
+            let eta = self.eta / libm::sqrtf(self.gradients[i]);
+            let mut sum = 0.0;
+            for (e, g) in e.iter_mut().zip(g) {
+                let ge = w * (-value * g + self.lambda2 * *e);
+                sum += ge * ge;
+                *e -= eta * ge;
+            }
+            if self.lambda1 > 0.0 {
+                soft_threshold(e, w * self.lambda1 * eta);
+            }
+            if self.nmf {
+                e.iter_mut().for_each(|v| *v = v.max(0.0));
+            }
+            self.gradients[i] += sum / k as f32;
+            self.trained[i] = true;
+        }
+    }
+
+    // returns the factors for each row or column and the feature embeddings
+    fn finish(mut self) -> (Vec<f32>, Vec<f32>) {
+        let k = self.k;
+        for (e, &trained) in self.embeddings.chunks_mut(k).zip(&self.trained) {
+            if !trained {
+                e.fill(f32::NAN);
+            }
+        }
+
+        let len = self.starts.len() - 1;
+        let mut factors = vec![f32::NAN; len * k];
+        for (index, out) in factors.chunks_mut(k).enumerate() {
+            let items = self.items(index);
+            if items.iter().any(|&(i, _)| self.trained[i]) {
+                out.fill(0.0);
+                for &(i, value) in items.iter().filter(|&&(i, _)| self.trained[i]) {
+                    for (o, e) in out.iter_mut().zip(&self.embeddings[i * k..(i + 1) * k]) {
+                        *o += value * e;
+                    }
+                }
+            }
+        }
+        let embeddings = self.embeddings.split_off(self.identities * k);
+        (factors, embeddings)
+    }
+}
+
+// stochastic gradient descent on the sums of embeddings, with the same updates as
+// the weighted solver (embeddings are shared between rows, so this uses one thread)
+//
+// `param` can differ from `params` when retrying or training a single iteration, and
+// the schedule uses the iterations from `params`, so training can stop early with the
+// same learning rates
+pub(crate) fn train(
+    data: &Matrix,
+    row_features: &Matrix,
+    column_features: &Matrix,
+    param: &MfParameter,
+    params: &Params,
+) -> Result<HybridModel, Error> {
+    let k = param.k as usize;
+    let mut rng = Rng(SEED);
+    let mut rows = Side::new(
+        data.rows() as usize,
+        row_features,
+        (param.lambda_p1, param.lambda_p2),
+        param,
+        &mut rng,
+    );
+    let mut columns = Side::new(
+        data.columns() as usize,
+        column_features,
+        (param.lambda_q1, param.lambda_q2),
+        param,
+        &mut rng,
+    );
+
+    let mut biases = Biases::new(
+        params.get_row_bias(),
+        params.get_column_bias(),
+        rows.identities,
+        columns.identities,
+    );
+    let lambda_bias = params.get_lambda_bias();
+
+    // shuffle once so updates are not in the order of the data
+    let mut order: Vec<usize> = (0..data.len()).collect();
+    rng.shuffle(&mut order);
+
+    #[cfg(feature = "std")]
+    let total_weight: f64 = (0..data.len()).map(|i| data.weight(i) as f64).sum();
+    #[cfg(feature = "std")]
+    if !param.quiet {
+        std::println!("{:>4}{:>13}", "iter", "tr_loss");
+    }
+
+    let mut monitor = Monitor::new(params.get_divergence_check());
+    let schedule = params.get_learning_rate_schedule();
+    let mut p = vec![0.0; k];
+    let mut q = vec![0.0; k];
+    for iter in 0..param.nr_iters {
+        let eta = schedule.learning_rate(param.eta, iter, params.get_iterations());
+        rows.eta = eta;
+        columns.eta = eta;
+        let mut loss = 0.0;
+        for &i in &order {
+            let x = &data.data[i];
+            let w = data.weight(i);
+            let (u, v) = (x.u as usize, x.v as usize);
+            rows.sum(u, &mut p);
+            columns.sum(v, &mut q);
+
+            let z = dot(&p, &q) + biases.predict(x.u, x.v);
+            let (g, l) = gradient(param.fun, x.r, z);
+            loss += w as f64 * l as f64;
+            if w == 0.0 {
+                continue;
+            }
+
+            if let Some(b) = biases.rows.get_mut(u) {
+                bias::step(b, g, w, eta, lambda_bias);
+            }
+            if let Some(b) = biases.columns.get_mut(v) {
+                bias::step(b, g, w, eta, lambda_bias);
+            }
+
+            // both sides use the factors from before the update
+            p.iter_mut().for_each(|v| *v *= g);
+            q.iter_mut().for_each(|v| *v *= g);
+            rows.update(u, &q, w);
+            columns.update(v, &p, w);
+        }
+
+        #[cfg(feature = "std")]
+        if !param.quiet {
+            std::println!("{:>4}{:>13.4}", iter, loss / total_weight);
+        }
+        monitor.check(iter, loss)?;
+    }
+
+    let (p, row_embeddings) = rows.finish();
+    let (q, column_embeddings) = columns.finish();
+    // rows and columns with features but no entries have a bias of zero
+    biases.resize((p.len() / k) as i32, (q.len() / k) as i32);
+    Ok(HybridModel {
+        loss: param.fun,
+        factors: k,
+        bias: weighted_mean(data),
+        p,
+        q,
+        row_embeddings,
+        column_embeddings,
+        biases,
+        metadata: ModelMetadata::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DivergenceCheck, Error, Loss, Matrix, Model, ParameterError, Solver};
+
+    // the value depends on whether the row and column are in the same group,
+    // and the group of each column is a feature
+    fn generate_data() -> (Matrix, Matrix) {
+        let mut data = Matrix::new();
+        for i in 0..20 {
+            // the last column has no entries
+            for j in 0..9 {
+                let value = if i % 2 == j % 2 { 3.0 } else { 1.0 };
+                data.push(i, j, value);
+            }
+        }
+        let mut column_features = Matrix::new();
+        for j in 0..10 {
+            column_features.push(j, j % 2, 1.0);
+        }
+        (data, column_features)
+    }
+
+    #[test]
+    fn test_fit_hybrid() {
+        let (data, column_features) = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .fit_hybrid(&data, &Matrix::new(), &column_features)
+            .unwrap();
+        assert_eq!(model.rows(), 20);
+        assert_eq!(model.columns(), 10);
+        assert_eq!(model.factors(), 8);
+        assert!(model.evaluator().unwrap().rmse(&data).unwrap() < 0.15);
+        assert_eq!(model.metadata().nnz, Some(180));
+    }
+
+    #[test]
+    fn test_new_column() {
+        let (data, column_features) = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .fit_hybrid(&data, &Matrix::new(), &column_features)
+            .unwrap();
+
+        // included in the model from its features
+        assert!(model.predict(1, 9) > model.predict(0, 9) + 0.5);
+
+        // the same as a column described by its features after training
+        assert_eq!(
+            model.predict_new_column(1, &[(1, 1.0)]),
+            model.predict(1, 9)
+        );
+        assert_eq!(
+            model.q_from_features(&[(1, 1.0)]).unwrap(),
+            model.q(9).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_new_row() {
+        let (data, column_features) = generate_data();
+        let mut row_features = Matrix::new();
+        for i in 0..20 {
+            row_features.push(i, i % 2, 1.0);
+        }
+        let model = Model::params()
+            .quiet(true)
+            .fit_hybrid(&data.transpose(), &column_features, &row_features)
+            .unwrap();
+        let same = model.predict_new_row(&[(1, 1.0)], 1);
+        let different = model.predict_new_row(&[(0, 1.0)], 1);
+        assert!(same > different + 0.5);
+    }
+
+    #[test]
+    fn test_unknown() {
+        let (data, column_features) = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .fit_hybrid(&data, &Matrix::new(), &column_features)
+            .unwrap();
+        assert_eq!(model.predict(20, 0), model.bias());
+        assert_eq!(model.predict(-1, 0), model.bias());
+        assert!(model.q_from_features(&[(2, 1.0)]).is_none());
+        assert!(model.q_from_features(&[(-1, 1.0)]).is_none());
+        assert_eq!(model.predict_new_column(0, &[]), model.bias());
+    }
+
+    #[test]
+    fn test_no_features() {
+        let (data, _) = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .fit_hybrid(&data, &Matrix::new(), &Matrix::new())
+            .unwrap();
+        assert_eq!(model.columns(), 9);
+        assert!(model.evaluator().unwrap().rmse(&data).unwrap() < 0.15);
+    }
+
+    #[test]
+    fn test_bias() {
+        let (data, column_features) = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .row_bias(true)
+            .column_bias(true)
+            .fit_hybrid(&data, &Matrix::new(), &column_features)
+            .unwrap();
+        assert!(model.row_bias(0).is_some());
+        assert_eq!(model.column_bias(9), Some(0.0));
+        assert!(model.evaluator().unwrap().rmse(&data).unwrap() < 0.15);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (data, column_features) = generate_data();
+        let empty = Matrix::new();
+
+        let result =
+            Model::params()
+                .loss(Loss::OneClassL2)
+                .fit_hybrid(&data, &empty, &column_features);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::FeatureLoss(Loss::OneClassL2).into()
+        );
+
+        let mut features = Matrix::new();
+        features.push(0, 0, 1.0);
+        features.push(1, 0, f32::NAN);
+        let result = Model::params().fit_hybrid(&data, &features, &empty);
+        assert_eq!(result.unwrap_err(), ParameterError::FeatureValue(1).into());
+
+        features.push(-1, 0, 1.0);
+        let result = Model::params().fit_hybrid(&data, &empty, &features);
+        assert_eq!(result.unwrap_err(), Error::Node(2));
+
+        let result = Model::params().fit_hybrid(&empty, &empty, &empty);
+        assert_eq!(result.unwrap_err(), ParameterError::NoData.into());
+    }
+
+    #[test]
+    fn test_diverged() {
+        let (data, column_features) = generate_data();
+        let mut params = Model::params();
+        params
+            .quiet(true)
+            .learning_rate(1e4)
+            .divergence_check(DivergenceCheck::AfterTraining);
+        let result = params.fit_hybrid(&data, &Matrix::new(), &column_features);
+        assert_eq!(result.unwrap_err(), Error::Diverged { iteration: 20 });
+
+        params
+            .solver(Solver::RustSgd)
+            .divergence_check(DivergenceCheck::PerIteration);
+        match params.fit_hybrid(&data, &Matrix::new(), &column_features) {
+            Err(Error::Diverged { iteration }) => assert!(iteration < 20),
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        }
+
+        let model = params
+            .divergence_retries(20)
+            .fit_hybrid(&data, &Matrix::new(), &column_features)
+            .unwrap();
+        let learning_rate = model
+            .metadata()
+            .params
+            .as_ref()
+            .unwrap()
+            .get_learning_rate();
+        assert!(learning_rate < 1e4);
+    }
+}
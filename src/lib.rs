@@ -3,16 +3,53 @@
 #![allow(clippy::needless_doctest_main)]
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+mod als;
+#[cfg(feature = "arrow")]
+mod arrow;
+mod bias;
 mod bindings;
+mod breakdown;
+#[cfg(any(feature = "ndarray", feature = "nalgebra"))]
+mod dense;
+mod divergence;
 mod error;
+mod evaluator;
+mod hybrid;
 mod matrix;
+mod metadata;
+mod metrics;
 mod model;
+mod observed;
 mod params;
 mod problem;
+mod quality;
+mod report;
+mod schedule;
+mod sgd;
+mod sparse;
+mod summary;
+mod weighting;
 
+pub use als::Solver;
 pub use bindings::Loss;
-pub use error::Error;
-pub use matrix::Matrix;
+pub use breakdown::{ActivityBucket, ErrorBreakdown, GroupError};
+pub use divergence::DivergenceCheck;
+pub use error::{Error, IoError, ParameterError, SparseError};
+
+#[cfg(feature = "arrow")]
+pub use error::RecordBatchError;
+pub use evaluator::Evaluator;
+pub use hybrid::HybridModel;
+pub use matrix::{IndexMaps, Matrix, MatrixIntoIter, MatrixIter};
+pub use metadata::ModelMetadata;
 pub use model::Model;
 pub use params::Params;
+pub use quality::{DedupPolicy, ValidationReport};
+pub use report::EvaluationReport;
+pub use schedule::LearningRateSchedule;
+pub use sparse::CompressedMatrix;
+pub use summary::{FactorSummary, ModelSummary};
+pub use weighting::Axis;
@@ -1,29 +1,180 @@
-use crate::bindings::MfNode;
+use crate::bindings::{MfNode, MfProblem};
+use crate::quality::{self, DedupPolicy, ValidationReport};
+use crate::sparse::{self, CompressedMatrix};
+use crate::weighting::{self, Axis};
+use crate::{Error, ParameterError};
+use alloc::vec;
 use alloc::vec::Vec;
+use core::iter::FusedIterator;
+use core::slice;
 
 /// A matrix.
 pub struct Matrix {
     pub(crate) data: Vec<MfNode>,
+    // empty when every entry has a weight of one
+    pub(crate) weights: Vec<f32>,
 }
 
 impl Matrix {
     /// Creates a new matrix.
     pub fn new() -> Self {
-        Self { data: Vec::new() }
+        Self {
+            data: Vec::new(),
+            weights: Vec::new(),
+        }
     }
 
     /// Creates a new matrix with a minimum capacity.
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             data: Vec::with_capacity(capacity),
+            weights: Vec::new(),
         }
     }
 
+    /// Creates a matrix from compressed sparse row (CSR) format.
+    pub fn from_csr(indptr: &[usize], indices: &[usize], data: &[f32]) -> Result<Self, Error> {
+        Ok(Self {
+            data: sparse::from_compressed(indptr, indices, data, false)?,
+            weights: Vec::new(),
+        })
+    }
+
+    /// Creates a matrix from compressed sparse column (CSC) format.
+    pub fn from_csc(indptr: &[usize], indices: &[usize], data: &[f32]) -> Result<Self, Error> {
+        Ok(Self {
+            data: sparse::from_compressed(indptr, indices, data, true)?,
+            weights: Vec::new(),
+        })
+    }
+
+    /// Returns the matrix in compressed sparse row (CSR) format.
+    ///
+    /// Entries are sorted by column within each row. Weights are not included.
+    pub fn to_csr(&self) -> Result<CompressedMatrix, Error> {
+        // check for invalid nodes
+        let _: MfProblem = self.try_into()?;
+        Ok(sparse::to_compressed(
+            &self.data,
+            self.rows() as usize,
+            false,
+        ))
+    }
+
+    /// Returns the matrix in compressed sparse column (CSC) format.
+    ///
+    /// Entries are sorted by row within each column. Weights are not included.
+    pub fn to_csc(&self) -> Result<CompressedMatrix, Error> {
+        // check for invalid nodes
+        let _: MfProblem = self.try_into()?;
+        Ok(sparse::to_compressed(
+            &self.data,
+            self.columns() as usize,
+            true,
+        ))
+    }
+
     /// Returns if the matrix is empty.
     pub fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
 
+    /// Returns the number of entries in the matrix.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns the number of rows (largest row index plus one).
+    ///
+    /// Invalid indices (negative or `i32::MAX`) are ignored.
+    pub fn rows(&self) -> i32 {
+        count(self.data.iter().map(|x| x.u))
+    }
+
+    /// Returns the number of columns (largest column index plus one).
+    ///
+    /// Invalid indices (negative or `i32::MAX`) are ignored.
+    pub fn columns(&self) -> i32 {
+        count(self.data.iter().map(|x| x.v))
+    }
+
+    /// Returns the number of entries in each row.
+    ///
+    /// Entries with invalid indices are ignored.
+    pub fn nnz_per_row(&self) -> Vec<usize> {
+        histogram(self.data.iter().map(|x| x.u), self.rows())
+    }
+
+    /// Returns the number of entries in each column.
+    ///
+    /// Entries with invalid indices are ignored.
+    pub fn nnz_per_column(&self) -> Vec<usize> {
+        histogram(self.data.iter().map(|x| x.v), self.columns())
+    }
+
+    /// Returns the mean value, ignoring NaN.
+    pub fn mean(&self) -> Option<f32> {
+        let mut sum = 0.0;
+        let mut count = 0;
+        for r in self.values() {
+            sum += r as f64;
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        Some((sum / count as f64) as f32)
+    }
+
+    /// Returns the smallest value, ignoring NaN.
+    pub fn min_value(&self) -> Option<f32> {
+        self.values().reduce(f32::min)
+    }
+
+    /// Returns the largest value, ignoring NaN.
+    pub fn max_value(&self) -> Option<f32> {
+        self.values().reduce(f32::max)
+    }
+
+    /// Returns the fraction of row and column pairs without an entry.
+    ///
+    /// Duplicate entries are counted once, and entries with invalid indices are ignored.
+    pub fn sparsity(&self) -> f64 {
+        let size = self.rows() as f64 * self.columns() as f64;
+        if size == 0.0 {
+            return 1.0;
+        }
+        let mut pairs: Vec<(i32, i32)> = self
+            .data
+            .iter()
+            .filter(|x| quality::valid_index(x.u) && quality::valid_index(x.v))
+            .map(|x| (x.u, x.v))
+            .collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+        1.0 - pairs.len() as f64 / size
+    }
+
+    /// Returns an iterator over the entries as `(row_index, column_index, value)`.
+    pub fn iter(&self) -> MatrixIter<'_> {
+        MatrixIter {
+            inner: self.data.iter(),
+        }
+    }
+
+    fn values(&self) -> impl Iterator<Item = f32> + '_ {
+        self.data.iter().map(|x| x.r).filter(|r| !r.is_nan())
+    }
+
+    /// Returns the weight of each entry, or `None` if no weights were added.
+    pub fn weights(&self) -> Option<&[f32]> {
+        if self.weights.is_empty() {
+            None
+        } else {
+            Some(&self.weights)
+        }
+    }
+
     /// Adds a value to the matrix.
     pub fn push(&mut self, row_index: i32, column_index: i32, value: f32) {
         self.data.push(MfNode {
@@ -31,6 +182,191 @@ impl Matrix {
             v: column_index,
             r: value,
         });
+        if !self.weights.is_empty() {
+            self.weights.push(1.0);
+        }
+    }
+
+    /// Adds a value with a weight to the matrix.
+    ///
+    /// The weight scales the gradient for the entry during training, so a weight of two
+    /// is like adding the entry twice. Entries added with `push` have a weight of one.
+    pub fn push_weighted(&mut self, row_index: i32, column_index: i32, value: f32, weight: f32) {
+        if self.weights.is_empty() {
+            self.weights.resize(self.data.len(), 1.0);
+        }
+        self.data.push(MfNode {
+            u: row_index,
+            v: column_index,
+            r: value,
+        });
+        self.weights.push(weight);
+    }
+
+    /// Returns a report of data quality problems.
+    pub fn validate(&self) -> ValidationReport {
+        ValidationReport::new(&self.data)
+    }
+
+    /// Removes entries with invalid indices or non-finite values and handles duplicates.
+    ///
+    /// Entries stay in the order pushed. Merged entries get the mean of their weights.
+    pub fn sanitize(&mut self, policy: DedupPolicy) {
+        quality::sanitize(&mut self.data, &mut self.weights, policy);
+    }
+
+    /// Aggregates entries with the same row and column.
+    ///
+    /// Entries are sorted by row and column afterwards. Merged entries get the mean of their weights.
+    pub fn dedup_by(&mut self, policy: DedupPolicy) {
+        quality::dedup(&mut self.data, &mut self.weights, policy);
+    }
+
+    /// Returns a matrix with rows and columns swapped.
+    pub fn transpose(&self) -> Matrix {
+        self.with_data(
+            self.data
+                .iter()
+                .map(|x| MfNode {
+                    u: x.v,
+                    v: x.u,
+                    r: x.r,
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns a matrix with the entries that match a predicate.
+    pub fn filter<F: FnMut(i32, i32, f32) -> bool>(&self, mut f: F) -> Matrix {
+        let mut matrix = Matrix::new();
+        for (i, x) in self.data.iter().enumerate() {
+            if f(x.u, x.v, x.r) {
+                match self.weights.get(i) {
+                    Some(&weight) => matrix.push_weighted(x.u, x.v, x.r, weight),
+                    None => matrix.push(x.u, x.v, x.r),
+                }
+            }
+        }
+        matrix
+    }
+
+    /// Removes rows and columns with too few entries.
+    ///
+    /// Removing entries can cause other rows and columns to fall below the minimum,
+    /// so this repeats until no more entries are removed.
+    pub fn retain_min_support(&mut self, min_row_nnz: usize, min_col_nnz: usize) {
+        loop {
+            let row_nnz = self.nnz_per_row();
+            let column_nnz = self.nnz_per_column();
+            let support = |nnz: &[usize], i: i32| nnz.get(i as usize).copied().unwrap_or(0);
+
+            let len = self.data.len();
+            self.retain(|x| {
+                support(&row_nnz, x.u) >= min_row_nnz && support(&column_nnz, x.v) >= min_col_nnz
+            });
+            if self.data.len() == len {
+                break;
+            }
+        }
+    }
+
+    /// Renumbers rows and columns so there are no empty ones.
+    ///
+    /// Returns the new index for each old row and column index.
+    pub fn compact(&mut self) -> Result<IndexMaps, Error> {
+        // check for invalid nodes
+        let _: MfProblem = (&*self).try_into()?;
+
+        let maps = IndexMaps {
+            rows: index_map(&self.nnz_per_row()),
+            columns: index_map(&self.nnz_per_column()),
+        };
+        for x in &mut self.data {
+            x.u = maps.rows[x.u as usize].unwrap();
+            x.v = maps.columns[x.v as usize].unwrap();
+        }
+        Ok(maps)
+    }
+
+    /// Returns a matrix with confidence `1 + alpha * log(1 + value / eps)` (for implicit feedback).
+    pub fn log_confidence(&self, alpha: f32, eps: f32) -> Result<Matrix, Error> {
+        if !(eps > 0.0 && eps.is_finite()) {
+            return Err(ParameterError::Eps(eps).into());
+        }
+        Ok(self.map_values(|r| 1.0 + alpha * libm::log1pf(r / eps)))
+    }
+
+    /// Returns a matrix with confidence `1 + alpha * value` (for implicit feedback).
+    pub fn linear_confidence(&self, alpha: f32) -> Matrix {
+        self.map_values(|r| 1.0 + alpha * r)
+    }
+
+    /// Returns a matrix with BM25 weighting (for implicit feedback).
+    ///
+    /// `k1` must be finite and non-negative, and `b` must be between 0 and 1.
+    pub fn bm25_weight(&self, axis: Axis, k1: f32, b: f32) -> Result<Matrix, Error> {
+        if !k1.is_finite() || k1 < 0.0 {
+            return Err(ParameterError::K1(k1).into());
+        }
+        if !(0.0..=1.0).contains(&b) {
+            return Err(ParameterError::B(b).into());
+        }
+        // check for invalid nodes
+        let _: MfProblem = self.try_into()?;
+        Ok(self.with_data(weighting::bm25(&self.data, axis, k1, b)))
+    }
+
+    /// Returns a matrix with TF-IDF weighting (for implicit feedback).
+    ///
+    /// The inverse document frequency is `1 + log(documents / frequency)`, so every weight is positive.
+    pub fn tfidf_weight(&self, axis: Axis) -> Result<Matrix, Error> {
+        // check for invalid nodes
+        let _: MfProblem = self.try_into()?;
+        Ok(self.with_data(weighting::tfidf(&self.data, axis)))
+    }
+
+    fn map_values<F: Fn(f32) -> f32>(&self, f: F) -> Matrix {
+        self.with_data(
+            self.data
+                .iter()
+                .map(|x| MfNode {
+                    u: x.u,
+                    v: x.v,
+                    r: f(x.r),
+                })
+                .collect(),
+        )
+    }
+
+    // the entries must be in the same order so the weights still apply
+    pub(crate) fn with_data(&self, data: Vec<MfNode>) -> Matrix {
+        Matrix {
+            data,
+            weights: self.weights.clone(),
+        }
+    }
+
+    fn retain<F: FnMut(&MfNode) -> bool>(&mut self, mut f: F) {
+        if self.weights.is_empty() {
+            self.data.retain(f);
+            return;
+        }
+
+        let keep: Vec<bool> = self.data.iter().map(&mut f).collect();
+        let mut i = 0;
+        self.data.retain(|_| {
+            i += 1;
+            keep[i - 1]
+        });
+        let mut i = 0;
+        self.weights.retain(|_| {
+            i += 1;
+            keep[i - 1]
+        });
+    }
+
+    pub(crate) fn weight(&self, index: usize) -> f32 {
+        self.weights.get(index).copied().unwrap_or(1.0)
     }
 }
 
@@ -40,9 +376,148 @@ impl Default for Matrix {
     }
 }
 
+/// The new indices after compacting a matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexMaps {
+    /// The new index for each old row index (`None` for empty rows).
+    pub rows: Vec<Option<i32>>,
+    /// The new index for each old column index (`None` for empty columns).
+    pub columns: Vec<Option<i32>>,
+}
+
+// ignores invalid indices, so the count always fits in an i32
+fn count<I: Iterator<Item = i32>>(indices: I) -> i32 {
+    indices
+        .filter(|&i| quality::valid_index(i))
+        .max()
+        .map_or(0, |i| i + 1)
+}
+
+fn histogram<I: Iterator<Item = i32>>(indices: I, len: i32) -> Vec<usize> {
+    let mut counts = vec![0; len as usize];
+    for i in indices {
+        if quality::valid_index(i) {
+            counts[i as usize] += 1;
+        }
+    }
+    counts
+}
+
+// assigns new indices in order to non-empty rows or columns
+fn index_map(nnz: &[usize]) -> Vec<Option<i32>> {
+    let mut next = 0;
+    nnz.iter()
+        .map(|&n| {
+            if n == 0 {
+                return None;
+            }
+            next += 1;
+            Some(next - 1)
+        })
+        .collect()
+}
+
+fn entry(x: &MfNode) -> (i32, i32, f32) {
+    (x.u, x.v, x.r)
+}
+
+/// An iterator over the entries of a matrix.
+pub struct MatrixIter<'a> {
+    inner: slice::Iter<'a, MfNode>,
+}
+
+impl Iterator for MatrixIter<'_> {
+    type Item = (i32, i32, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for MatrixIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(entry)
+    }
+}
+
+impl ExactSizeIterator for MatrixIter<'_> {}
+
+impl FusedIterator for MatrixIter<'_> {}
+
+/// An owning iterator over the entries of a matrix.
+pub struct MatrixIntoIter {
+    inner: vec::IntoIter<MfNode>,
+}
+
+impl Iterator for MatrixIntoIter {
+    type Item = (i32, i32, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|x| entry(&x))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for MatrixIntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|x| entry(&x))
+    }
+}
+
+impl ExactSizeIterator for MatrixIntoIter {}
+
+impl FusedIterator for MatrixIntoIter {}
+
+impl<'a> IntoIterator for &'a Matrix {
+    type Item = (i32, i32, f32);
+    type IntoIter = MatrixIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for Matrix {
+    type Item = (i32, i32, f32);
+    type IntoIter = MatrixIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        MatrixIntoIter {
+            inner: self.data.into_iter(),
+        }
+    }
+}
+
+impl FromIterator<(i32, i32, f32)> for Matrix {
+    fn from_iter<I: IntoIterator<Item = (i32, i32, f32)>>(iter: I) -> Self {
+        let mut matrix = Matrix::new();
+        matrix.extend(iter);
+        matrix
+    }
+}
+
+impl Extend<(i32, i32, f32)> for Matrix {
+    fn extend<I: IntoIterator<Item = (i32, i32, f32)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.data.reserve(iter.size_hint().0);
+        for (row_index, column_index, value) in iter {
+            self.push(row_index, column_index, value);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::Matrix;
+    use crate::{Axis, DedupPolicy, Error, Matrix, ParameterError, SparseError};
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
 
     #[test]
     fn test_new() {
@@ -55,4 +530,537 @@ mod tests {
         let mut data = Matrix::with_capacity(1);
         data.push(0, 0, 1.0);
     }
+
+    #[test]
+    fn test_csr() {
+        let data = Matrix::from_csr(&[0, 2, 2, 3], &[1, 0, 2], &[1.0, 2.0, 3.0]).unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 1, 1.0), (0, 0, 2.0), (2, 2, 3.0)]);
+
+        let csr = data.to_csr().unwrap();
+        assert_eq!(csr.indptr, [0, 2, 2, 3]);
+        assert_eq!(csr.indices, [0, 1, 2]);
+        assert_eq!(csr.data, [2.0, 1.0, 3.0]);
+    }
+
+    #[test]
+    fn test_csc() {
+        let data = Matrix::from_csc(&[0, 2, 2, 3], &[1, 0, 2], &[1.0, 2.0, 3.0]).unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(1, 0, 1.0), (0, 0, 2.0), (2, 2, 3.0)]);
+
+        let csc = data.to_csc().unwrap();
+        assert_eq!(csc.indptr, [0, 2, 2, 3]);
+        assert_eq!(csc.indices, [0, 1, 2]);
+        assert_eq!(csc.data, [2.0, 1.0, 3.0]);
+    }
+
+    #[test]
+    fn test_csr_empty() {
+        let data = Matrix::from_csr(&[0], &[], &[]).unwrap();
+        assert!(data.is_empty());
+
+        let csr = data.to_csr().unwrap();
+        assert_eq!(csr.indptr, [0]);
+        assert!(csr.indices.is_empty());
+    }
+
+    #[test]
+    fn test_csr_invalid() {
+        let err = |indptr: &[usize], indices: &[usize], data: &[f32]| match Matrix::from_csr(
+            indptr, indices, data,
+        )
+        .err()
+        .unwrap()
+        {
+            Error::Sparse(err) => err,
+            err => panic!("unexpected error: {:?}", err),
+        };
+        assert_eq!(
+            err(&[0, 1], &[0], &[]),
+            SparseError::Length {
+                indices: 1,
+                data: 0
+            }
+        );
+        assert_eq!(err(&[], &[], &[]), SparseError::IndptrStart);
+        assert_eq!(err(&[0, 2], &[0], &[1.0]), SparseError::IndptrEnd);
+        assert_eq!(
+            err(&[0, 2, 1, 2], &[0, 1], &[1.0, 2.0]),
+            SparseError::IndptrOrder(2)
+        );
+        assert_eq!(
+            err(&[0, 1], &[usize::MAX], &[1.0]),
+            SparseError::IndexOutOfRange(usize::MAX)
+        );
+        assert_eq!(
+            Matrix::from_csr(&[0, 2, 1, 2], &[0, 1], &[1.0, 2.0])
+                .err()
+                .unwrap()
+                .to_string(),
+            "invalid sparse matrix: indptr must be non-decreasing (at position 2)"
+        );
+
+        let mut data = Matrix::new();
+        data.push(-1, 0, 1.0);
+        assert_eq!(data.to_csr().err(), Some(Error::Node(0)));
+    }
+
+    #[cfg(feature = "sprs")]
+    #[test]
+    fn test_sprs() {
+        let mut data = Matrix::new();
+        data.push(0, 1, 1.0);
+        data.push(2, 0, 2.0);
+        data.push(0, 1, 3.0);
+
+        let mat = data.to_sprs().unwrap();
+        assert_eq!(mat.shape(), (3, 2));
+        assert_eq!(mat.get(0, 1), Some(&4.0));
+        assert_eq!(mat.get(2, 0), Some(&2.0));
+
+        let data = Matrix::try_from(&mat).unwrap();
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 1, 4.0), (2, 0, 2.0)]);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(2, 1, 3.0);
+        data.push(0, 3, 2.0);
+        data.push(2, 1, f32::NAN);
+
+        assert_eq!(data.len(), 4);
+        assert_eq!(data.rows(), 3);
+        assert_eq!(data.columns(), 4);
+        assert_eq!(data.nnz_per_row(), [2, 0, 2]);
+        assert_eq!(data.nnz_per_column(), [1, 2, 0, 1]);
+        assert_eq!(data.mean(), Some(2.0));
+        assert_eq!(data.min_value(), Some(1.0));
+        assert_eq!(data.max_value(), Some(3.0));
+        assert_eq!(data.sparsity(), 1.0 - 3.0 / 12.0);
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        let data = Matrix::new();
+        assert_eq!(data.len(), 0);
+        assert_eq!(data.rows(), 0);
+        assert_eq!(data.columns(), 0);
+        assert!(data.nnz_per_row().is_empty());
+        assert_eq!(data.mean(), None);
+        assert_eq!(data.min_value(), None);
+        assert_eq!(data.max_value(), None);
+        assert_eq!(data.sparsity(), 1.0);
+    }
+
+    #[test]
+    fn test_stats_invalid_index() {
+        let mut data = Matrix::new();
+        data.push(-1, 1, 1.0);
+        data.push(1, -1, 3.0);
+        assert_eq!(data.rows(), 2);
+        assert_eq!(data.columns(), 2);
+        assert_eq!(data.nnz_per_row(), [0, 1]);
+        assert_eq!(data.nnz_per_column(), [0, 1]);
+        assert_eq!(data.mean(), Some(2.0));
+        assert_eq!(data.sparsity(), 1.0);
+
+        data.push(i32::MAX, i32::MAX, 1.0);
+        assert_eq!(data.rows(), 2);
+        assert_eq!(data.columns(), 2);
+        assert_eq!(data.nnz_per_row(), [0, 1]);
+        assert_eq!(data.nnz_per_column(), [0, 1]);
+    }
+
+    #[test]
+    fn test_stats_duplicates() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(0, 0, 2.0);
+        data.push(0, 0, 3.0);
+        assert_eq!(data.sparsity(), 0.0);
+
+        let mut data = Matrix::new();
+        data.push(0, 0, f32::NAN);
+        assert_eq!(data.mean(), None);
+        assert_eq!(data.min_value(), None);
+        assert_eq!(data.max_value(), None);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut data = Matrix::new();
+        data.push(0, 1, 1.0);
+        data.push(2, 3, 4.0);
+
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 1, 1.0), (2, 3, 4.0)]);
+        assert_eq!(data.iter().len(), 2);
+        assert_eq!(data.iter().next_back(), Some((2, 3, 4.0)));
+
+        let mut count = 0;
+        for (_, _, value) in &data {
+            count += value as usize;
+        }
+        assert_eq!(count, 5);
+
+        let entries: Vec<_> = data.into_iter().collect();
+        assert_eq!(entries, [(0, 1, 1.0), (2, 3, 4.0)]);
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let mut data: Matrix = [(0, 1, 1.0), (2, 3, 4.0)].into_iter().collect();
+        data.extend([(1, 1, 2.0)]);
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 1, 1.0), (2, 3, 4.0), (1, 1, 2.0)]);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let data: Matrix = [(0, 1, 1.0), (2, 3, 4.0)].into_iter().collect();
+        let entries: Vec<_> = data.transpose().iter().collect();
+        assert_eq!(entries, [(1, 0, 1.0), (3, 2, 4.0)]);
+    }
+
+    #[test]
+    fn test_filter() {
+        let data: Matrix = [(0, 1, 1.0), (2, 3, 4.0), (1, 1, 2.0)]
+            .into_iter()
+            .collect();
+        let entries: Vec<_> = data
+            .filter(|_, col, val| col == 1 && val > 1.0)
+            .iter()
+            .collect();
+        assert_eq!(entries, [(1, 1, 2.0)]);
+    }
+
+    #[test]
+    fn test_retain_min_support() {
+        let mut data: Matrix = [
+            (0, 0, 1.0),
+            (0, 1, 1.0),
+            (1, 0, 1.0),
+            (1, 1, 1.0),
+            (2, 0, 1.0),
+            (2, 2, 1.0),
+        ]
+        .into_iter()
+        .collect();
+
+        // removing column 2 leaves row 2 with one entry
+        data.retain_min_support(2, 2);
+        let entries: Vec<_> = data.iter().map(|x| (x.0, x.1)).collect();
+        assert_eq!(entries, [(0, 0), (0, 1), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut data: Matrix = [(4, 2, 1.0), (1, 5, 2.0), (4, 5, 3.0)]
+            .into_iter()
+            .collect();
+        let maps = data.compact().unwrap();
+        assert_eq!(maps.rows, [None, Some(0), None, None, Some(1)]);
+        assert_eq!(maps.columns, [None, None, Some(0), None, None, Some(1)]);
+
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(1, 0, 1.0), (0, 1, 2.0), (1, 1, 3.0)]);
+    }
+
+    #[test]
+    fn test_compact_invalid_node() {
+        let mut data = Matrix::new();
+        data.push(0, -1, 1.0);
+        assert_eq!(data.compact(), Err(Error::Node(0)));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(2, 1, 3.0);
+        data.push(0, 0, 2.0);
+        data.push(1, -1, 4.0);
+        data.push(2, 3, f32::NAN);
+        data.push(2, 2, f32::INFINITY);
+
+        let report = data.validate();
+        assert_eq!(report.len, 6);
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.columns, 4);
+        assert_eq!(report.invalid_indices, 1);
+        assert_eq!(report.nan_values, 1);
+        assert_eq!(report.infinite_values, 1);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.empty_rows, 1);
+        assert_eq!(report.empty_columns, 0);
+        assert_eq!(report.min_value, Some(1.0));
+        assert_eq!(report.max_value, Some(4.0));
+        assert_eq!(report.density, 4.0 / 12.0);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn test_validate_empty() {
+        let report = Matrix::new().validate();
+        assert_eq!(report.rows, 0);
+        assert_eq!(report.min_value, None);
+        assert_eq!(report.density, 0.0);
+        assert!(report.is_valid());
+    }
+
+    fn sanitized(policy: DedupPolicy) -> Vec<(i32, i32, f32)> {
+        let mut data = Matrix::new();
+        data.push(1, 1, 5.0);
+        data.push(0, 0, 1.0);
+        data.push(0, 1, f32::NAN);
+        data.push(0, 0, 2.0);
+        data.push(-1, 0, 1.0);
+        data.push(0, 0, 6.0);
+        data.sanitize(policy);
+        assert!(data.validate().is_valid());
+        data.data.iter().map(|x| (x.u, x.v, x.r)).collect()
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitized(DedupPolicy::First), [(1, 1, 5.0), (0, 0, 1.0)]);
+        assert_eq!(sanitized(DedupPolicy::Last), [(1, 1, 5.0), (0, 0, 6.0)]);
+        assert_eq!(sanitized(DedupPolicy::Mean), [(1, 1, 5.0), (0, 0, 3.0)]);
+        assert_eq!(sanitized(DedupPolicy::Sum), [(1, 1, 5.0), (0, 0, 9.0)]);
+        assert_eq!(sanitized(DedupPolicy::Max), [(1, 1, 5.0), (0, 0, 6.0)]);
+        assert_eq!(sanitized(DedupPolicy::Count), [(1, 1, 1.0), (0, 0, 3.0)]);
+        assert_eq!(sanitized(DedupPolicy::DropAll), [(1, 1, 5.0)]);
+    }
+
+    fn deduped(policy: DedupPolicy) -> Vec<(i32, i32, f32)> {
+        let mut data = Matrix::new();
+        data.push(1, 0, 5.0);
+        data.push(0, 1, 1.0);
+        data.push(1, 0, 2.0);
+        data.push(0, 0, 3.0);
+        data.push(1, 0, 4.0);
+        data.dedup_by(policy);
+        data.data.iter().map(|x| (x.u, x.v, x.r)).collect()
+    }
+
+    #[test]
+    fn test_dedup_by() {
+        assert_eq!(
+            deduped(DedupPolicy::Sum),
+            [(0, 0, 3.0), (0, 1, 1.0), (1, 0, 11.0)]
+        );
+        assert_eq!(
+            deduped(DedupPolicy::Mean),
+            [(0, 0, 3.0), (0, 1, 1.0), (1, 0, 11.0 / 3.0)]
+        );
+        assert_eq!(
+            deduped(DedupPolicy::Max),
+            [(0, 0, 3.0), (0, 1, 1.0), (1, 0, 5.0)]
+        );
+        assert_eq!(
+            deduped(DedupPolicy::First),
+            [(0, 0, 3.0), (0, 1, 1.0), (1, 0, 5.0)]
+        );
+        assert_eq!(
+            deduped(DedupPolicy::Last),
+            [(0, 0, 3.0), (0, 1, 1.0), (1, 0, 4.0)]
+        );
+        assert_eq!(
+            deduped(DedupPolicy::Count),
+            [(0, 0, 1.0), (0, 1, 1.0), (1, 0, 3.0)]
+        );
+        assert_eq!(deduped(DedupPolicy::DropAll), [(0, 0, 3.0), (0, 1, 1.0)]);
+    }
+
+    #[test]
+    fn test_dedup_by_empty() {
+        let mut data = Matrix::new();
+        data.dedup_by(DedupPolicy::Sum);
+        assert!(data.is_empty());
+    }
+
+    fn values(data: &Matrix) -> Vec<f32> {
+        data.data.iter().map(|x| x.r).collect()
+    }
+
+    fn assert_in_delta(expected: f32, actual: f32) {
+        assert!(
+            (expected - actual).abs() < 1e-5,
+            "{} != {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_confidence() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 0.0);
+        data.push(0, 1, 3.0);
+
+        assert_eq!(values(&data.linear_confidence(40.0)), [1.0, 121.0]);
+
+        let log = data.log_confidence(2.0, 0.5).unwrap();
+        assert_eq!(log.data[0].r, 1.0);
+        assert_in_delta(1.0 + 2.0 * 7.0f32.ln(), log.data[1].r);
+        assert_eq!((log.data[1].u, log.data[1].v), (0, 1));
+
+        for eps in [0.0, -1.0, f32::NAN] {
+            assert!(matches!(
+                data.log_confidence(2.0, eps),
+                Err(Error::Parameter(ParameterError::Eps(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_tfidf_weight() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 4.0);
+        data.push(1, 0, 1.0);
+        data.push(2, 1, 9.0);
+
+        // 3 documents, term 0 in 2 documents, term 1 in 1 document
+        let weighted = data.tfidf_weight(Axis::Rows).unwrap();
+        let idf0 = 1.0 + 1.5f32.ln();
+        let idf1 = 1.0 + 3.0f32.ln();
+        assert_in_delta(2.0 * idf0, weighted.data[0].r);
+        assert_in_delta(idf0, weighted.data[1].r);
+        assert_in_delta(3.0 * idf1, weighted.data[2].r);
+
+        // 2 documents, term 0 in 1 document
+        let weighted = data.tfidf_weight(Axis::Columns).unwrap();
+        assert_in_delta(2.0 * (1.0 + 2.0f32.ln()), weighted.data[0].r);
+
+        // term in every document
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 0, 1.0);
+        let weighted = data.tfidf_weight(Axis::Rows).unwrap();
+        assert!(weighted.data.iter().all(|x| x.r == 1.0));
+    }
+
+    #[test]
+    fn test_bm25_weight() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(0, 1, 1.0);
+        data.push(1, 1, 2.0);
+        data.push(2, 2, 1.0);
+
+        let (k1, b) = (100.0, 0.8);
+        let weighted = data.bm25_weight(Axis::Rows, k1, b).unwrap();
+
+        // document lengths are 2, 2, 1 with an average of 5 / 3
+        let idf1 = 1.0 + 1.5f32.ln();
+        let idf2 = 1.0 + 3.0f32.ln();
+        let norm0 = (1.0 - b) + b * 2.0 / (5.0 / 3.0);
+        let norm2 = (1.0 - b) + b * 1.0 / (5.0 / 3.0);
+        assert_in_delta((k1 + 1.0) / (k1 * norm0 + 1.0) * idf2, weighted.data[0].r);
+        assert_in_delta(
+            2.0 * (k1 + 1.0) / (k1 * norm0 + 2.0) * idf1,
+            weighted.data[2].r,
+        );
+        assert_in_delta((k1 + 1.0) / (k1 * norm2 + 1.0) * idf2, weighted.data[3].r);
+    }
+
+    #[test]
+    fn test_bm25_weight_invalid() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        assert_eq!(
+            data.bm25_weight(Axis::Rows, -1.0, 0.8).err(),
+            Some(ParameterError::K1(-1.0).into())
+        );
+        assert!(data.bm25_weight(Axis::Rows, f32::NAN, 0.8).is_err());
+        assert_eq!(
+            data.bm25_weight(Axis::Rows, 100.0, 1.5).err(),
+            Some(ParameterError::B(1.5).into())
+        );
+        assert_eq!(
+            data.bm25_weight(Axis::Rows, 100.0, -0.1).err(),
+            Some(ParameterError::B(-0.1).into())
+        );
+    }
+
+    #[test]
+    fn test_bm25_weight_zero_length() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 0.0);
+        data.push(1, 0, 0.0);
+        let weighted = data.bm25_weight(Axis::Rows, 100.0, 0.8).unwrap();
+        assert!(weighted.data.iter().all(|x| x.r == 0.0));
+        let weighted = data.bm25_weight(Axis::Rows, 0.0, 0.8).unwrap();
+        assert!(weighted.data.iter().all(|x| x.r == 0.0));
+    }
+
+    #[test]
+    fn test_weight_invalid_node() {
+        let mut data = Matrix::new();
+        data.push(-1, 0, 1.0);
+        assert_eq!(data.tfidf_weight(Axis::Rows).err(), Some(Error::Node(0)));
+        assert_eq!(
+            data.bm25_weight(Axis::Rows, 100.0, 0.8).err(),
+            Some(Error::Node(0))
+        );
+    }
+
+    #[test]
+    fn test_push_weighted() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        assert_eq!(data.weights(), None);
+        data.push_weighted(1, 0, 2.0, 3.0);
+        data.push(1, 1, 1.0);
+        assert_eq!(data.weights(), Some([1.0, 3.0, 1.0].as_slice()));
+
+        let transposed = data.transpose();
+        assert_eq!(transposed.weights(), data.weights());
+
+        let filtered = data.filter(|_, _, value| value > 1.0);
+        assert_eq!(filtered.weights(), Some([3.0].as_slice()));
+
+        let confidence = data.linear_confidence(1.0);
+        assert_eq!(confidence.weights(), data.weights());
+    }
+
+    #[test]
+    fn test_weights_sanitize() {
+        let mut data = Matrix::new();
+        data.push_weighted(0, 0, 1.0, 2.0);
+        data.push_weighted(1, 1, f32::NAN, 5.0);
+        data.push_weighted(0, 0, 3.0, 4.0);
+        data.push(2, 2, 1.0);
+        data.sanitize(DedupPolicy::Mean);
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 0, 2.0), (2, 2, 1.0)]);
+        assert_eq!(data.weights(), Some([3.0, 1.0].as_slice()));
+    }
+
+    #[test]
+    fn test_weights_dedup_by() {
+        let mut data = Matrix::new();
+        data.push_weighted(1, 0, 1.0, 2.0);
+        data.push_weighted(0, 0, 3.0, 4.0);
+        data.push_weighted(1, 0, 1.0, 6.0);
+        data.dedup_by(DedupPolicy::Sum);
+        let entries: Vec<_> = data.iter().collect();
+        assert_eq!(entries, [(0, 0, 3.0), (1, 0, 2.0)]);
+        assert_eq!(data.weights(), Some([4.0, 4.0].as_slice()));
+    }
+
+    #[test]
+    fn test_weights_retain_min_support() {
+        let mut data = Matrix::new();
+        data.push_weighted(0, 0, 1.0, 2.0);
+        data.push_weighted(0, 1, 1.0, 3.0);
+        data.push_weighted(1, 0, 1.0, 4.0);
+        data.retain_min_support(2, 1);
+        assert_eq!(data.len(), 2);
+        assert_eq!(data.weights(), Some([2.0, 3.0].as_slice()));
+    }
 }
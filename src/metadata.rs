@@ -0,0 +1,387 @@
+use crate::Params;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+/// Information about how a model was trained.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModelMetadata {
+    /// The parameters used for training.
+    pub params: Option<Params>,
+    /// The number of entries in the training set.
+    pub nnz: Option<u64>,
+    /// The time training finished, in seconds since the Unix epoch (requires `std`).
+    pub trained_at: Option<u64>,
+    /// The duration of training, in seconds (requires `std`).
+    pub training_time: Option<f64>,
+    /// The version of this crate used for training.
+    pub version: Option<String>,
+    /// User-supplied tags.
+    pub tags: BTreeMap<String, String>,
+}
+
+impl ModelMetadata {
+    pub(crate) fn new(params: &Params, nnz: usize, timer: Timer) -> Self {
+        let mut metadata = ModelMetadata {
+            params: Some(params.clone()),
+            nnz: Some(nnz as u64),
+            version: Some(env!("CARGO_PKG_VERSION").into()),
+            ..Default::default()
+        };
+        timer.finish(&mut metadata);
+        metadata
+    }
+}
+
+pub(crate) struct Timer {
+    #[cfg(feature = "std")]
+    start: std::time::Instant,
+}
+
+impl Timer {
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn finish(self, metadata: &mut ModelMetadata) {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        metadata.training_time = Some(self.start.elapsed().as_secs_f64());
+        metadata.trained_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs());
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn finish(self, _metadata: &mut ModelMetadata) {}
+}
+
+#[cfg(feature = "std")]
+mod file {
+    use super::ModelMetadata;
+    use crate::{DivergenceCheck, Error, IoError, LearningRateSchedule, Loss, Params, Solver};
+    use alloc::format;
+    use alloc::string::String;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    // LIBMF stops reading after the factors, so older versions can still load the file
+    const PREFIX: &str = "meta ";
+
+    impl ModelMetadata {
+        pub(crate) fn append(&self, path: &str) -> Result<(), Error> {
+            let io_error = |e| Error::Io(IoError::write(path).with_source(e));
+            let mut file = OpenOptions::new()
+                .append(true)
+                .open(path)
+                .map_err(io_error)?;
+            self.write(&mut file).map_err(io_error)
+        }
+
+        // parses a line if it is metadata, since it is read along with the other sections
+        pub(crate) fn read_line(
+            &mut self,
+            path: &str,
+            number: usize,
+            line: &str,
+        ) -> Result<(), Error> {
+            if let Some(entry) = line.strip_prefix(PREFIX) {
+                self.parse_entry(entry)
+                    .ok_or_else(|| Error::MalformedModel {
+                        path: path.into(),
+                        line: Some(number),
+                    })?;
+            }
+            Ok(())
+        }
+
+        fn write<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+            if let Some(params) = &self.params {
+                writeln!(w, "{}param loss {:?}", PREFIX, params.get_loss())?;
+                writeln!(w, "{}param factors {}", PREFIX, params.get_factors())?;
+                writeln!(w, "{}param threads {}", PREFIX, params.get_threads())?;
+                writeln!(w, "{}param bins {}", PREFIX, params.get_bins())?;
+                writeln!(w, "{}param iterations {}", PREFIX, params.get_iterations())?;
+                writeln!(w, "{}param lambda_p1 {}", PREFIX, params.get_lambda_p1())?;
+                writeln!(w, "{}param lambda_p2 {}", PREFIX, params.get_lambda_p2())?;
+                writeln!(w, "{}param lambda_q1 {}", PREFIX, params.get_lambda_q1())?;
+                writeln!(w, "{}param lambda_q2 {}", PREFIX, params.get_lambda_q2())?;
+                writeln!(
+                    w,
+                    "{}param learning_rate {}",
+                    PREFIX,
+                    params.get_learning_rate()
+                )?;
+                writeln!(w, "{}param alpha {}", PREFIX, params.get_alpha())?;
+                writeln!(w, "{}param c {}", PREFIX, params.get_c())?;
+                writeln!(w, "{}param nmf {}", PREFIX, params.get_nmf())?;
+                writeln!(w, "{}param quiet {}", PREFIX, params.get_quiet())?;
+                writeln!(
+                    w,
+                    "{}param divergence_check {:?}",
+                    PREFIX,
+                    params.get_divergence_check()
+                )?;
+                writeln!(
+                    w,
+                    "{}param divergence_retries {}",
+                    PREFIX,
+                    params.get_divergence_retries()
+                )?;
+                writeln!(w, "{}param row_bias {}", PREFIX, params.get_row_bias())?;
+                writeln!(
+                    w,
+                    "{}param column_bias {}",
+                    PREFIX,
+                    params.get_column_bias()
+                )?;
+                writeln!(
+                    w,
+                    "{}param lambda_bias {}",
+                    PREFIX,
+                    params.get_lambda_bias()
+                )?;
+                writeln!(w, "{}param solver {:?}", PREFIX, params.get_solver())?;
+                writeln!(
+                    w,
+                    "{}param learning_rate_schedule {}",
+                    PREFIX,
+                    format_schedule(params.get_learning_rate_schedule())
+                )?;
+            }
+            if let Some(nnz) = self.nnz {
+                writeln!(w, "{}nnz {}", PREFIX, nnz)?;
+            }
+            if let Some(trained_at) = self.trained_at {
+                writeln!(w, "{}trained_at {}", PREFIX, trained_at)?;
+            }
+            if let Some(training_time) = self.training_time {
+                writeln!(w, "{}training_time {}", PREFIX, training_time)?;
+            }
+            if let Some(version) = &self.version {
+                writeln!(w, "{}version {}", PREFIX, escape(version))?;
+            }
+            for (key, value) in &self.tags {
+                writeln!(w, "{}tag {} {}", PREFIX, escape(key), escape(value))?;
+            }
+            Ok(())
+        }
+
+        fn parse_entry(&mut self, entry: &str) -> Option<()> {
+            let mut parts = entry.split(' ');
+            match parts.next()? {
+                "param" => {
+                    let params = self.params.get_or_insert_with(Params::new);
+                    let name = parts.next()?;
+                    let value = parts.next()?;
+                    match name {
+                        "loss" => params.loss(parse_loss(value)?),
+                        "factors" => params.factors(value.parse().ok()?),
+                        "threads" => params.threads(value.parse().ok()?),
+                        "bins" => params.bins(value.parse().ok()?),
+                        "iterations" => params.iterations(value.parse().ok()?),
+                        "lambda_p1" => params.lambda_p1(value.parse().ok()?),
+                        "lambda_p2" => params.lambda_p2(value.parse().ok()?),
+                        "lambda_q1" => params.lambda_q1(value.parse().ok()?),
+                        "lambda_q2" => params.lambda_q2(value.parse().ok()?),
+                        "learning_rate" => params.learning_rate(value.parse().ok()?),
+                        "alpha" => params.alpha(value.parse().ok()?),
+                        "c" => params.c(value.parse().ok()?),
+                        "nmf" => params.nmf(value.parse().ok()?),
+                        "quiet" => params.quiet(value.parse().ok()?),
+                        "divergence_check" => {
+                            params.divergence_check(parse_divergence_check(value)?)
+                        }
+                        "divergence_retries" => params.divergence_retries(value.parse().ok()?),
+                        "row_bias" => params.row_bias(value.parse().ok()?),
+                        "column_bias" => params.column_bias(value.parse().ok()?),
+                        "lambda_bias" => params.lambda_bias(value.parse().ok()?),
+                        "solver" => params.solver(parse_solver(value)?),
+                        "learning_rate_schedule" => {
+                            params.learning_rate_schedule(parse_schedule(value)?)
+                        }
+                        // ignore parameters from newer versions
+                        _ => params,
+                    };
+                }
+                "nnz" => self.nnz = Some(parts.next()?.parse().ok()?),
+                "trained_at" => self.trained_at = Some(parts.next()?.parse().ok()?),
+                "training_time" => self.training_time = Some(parts.next()?.parse().ok()?),
+                "version" => self.version = Some(unescape(parts.next()?)?),
+                "tag" => {
+                    let key = unescape(parts.next()?)?;
+                    let value = unescape(parts.next()?)?;
+                    self.tags.insert(key, value);
+                }
+                // ignore entries from newer versions
+                _ => (),
+            }
+            Some(())
+        }
+    }
+
+    fn parse_loss(value: &str) -> Option<Loss> {
+        let loss = match value {
+            "RealL2" => Loss::RealL2,
+            "RealL1" => Loss::RealL1,
+            "RealKL" => Loss::RealKL,
+            "BinaryLog" => Loss::BinaryLog,
+            "BinaryL2" => Loss::BinaryL2,
+            "BinaryL1" => Loss::BinaryL1,
+            "OneClassRow" => Loss::OneClassRow,
+            "OneClassCol" => Loss::OneClassCol,
+            "OneClassL2" => Loss::OneClassL2,
+            _ => return None,
+        };
+        Some(loss)
+    }
+
+    fn parse_divergence_check(value: &str) -> Option<DivergenceCheck> {
+        let check = match value {
+            "None" => DivergenceCheck::None,
+            "AfterTraining" => DivergenceCheck::AfterTraining,
+            "PerIteration" => DivergenceCheck::PerIteration,
+            _ => return None,
+        };
+        Some(check)
+    }
+
+    fn parse_solver(value: &str) -> Option<Solver> {
+        let solver = match value {
+            "Sgd" => Solver::Sgd,
+            "RustSgd" => Solver::RustSgd,
+            "Als" => Solver::Als,
+            _ => return None,
+        };
+        Some(solver)
+    }
+
+    // the name and values separated by commas
+    fn format_schedule(schedule: LearningRateSchedule) -> String {
+        match schedule {
+            LearningRateSchedule::Constant => "Constant".into(),
+            LearningRateSchedule::Step { step_size, gamma } => {
+                format!("Step,{},{}", step_size, gamma)
+            }
+            LearningRateSchedule::Exponential { gamma } => format!("Exponential,{}", gamma),
+            LearningRateSchedule::Cosine { min_learning_rate } => {
+                format!("Cosine,{}", min_learning_rate)
+            }
+            LearningRateSchedule::WarmupCosine {
+                warmup,
+                min_learning_rate,
+            } => format!("WarmupCosine,{},{}", warmup, min_learning_rate),
+        }
+    }
+
+    fn parse_schedule(value: &str) -> Option<LearningRateSchedule> {
+        let mut parts = value.split(',');
+        let schedule = match parts.next()? {
+            "Constant" => LearningRateSchedule::Constant,
+            "Step" => LearningRateSchedule::Step {
+                step_size: parts.next()?.parse().ok()?,
+                gamma: parts.next()?.parse().ok()?,
+            },
+            "Exponential" => LearningRateSchedule::Exponential {
+                gamma: parts.next()?.parse().ok()?,
+            },
+            "Cosine" => LearningRateSchedule::Cosine {
+                min_learning_rate: parts.next()?.parse().ok()?,
+            },
+            "WarmupCosine" => LearningRateSchedule::WarmupCosine {
+                warmup: parts.next()?.parse().ok()?,
+                min_learning_rate: parts.next()?.parse().ok()?,
+            },
+            _ => return None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(schedule)
+    }
+
+    // keep each value a single token
+    fn escape(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            match c {
+                '%' => escaped.push_str("%25"),
+                ' ' => escaped.push_str("%20"),
+                '\t' => escaped.push_str("%09"),
+                '\n' => escaped.push_str("%0A"),
+                '\r' => escaped.push_str("%0D"),
+                _ => escaped.push(c),
+            }
+        }
+        if escaped.is_empty() {
+            escaped.push_str("%00");
+        }
+        escaped
+    }
+
+    fn unescape(value: &str) -> Option<String> {
+        if value == "%00" {
+            return Some(String::new());
+        }
+
+        let mut unescaped = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(i) = rest.find('%') {
+            unescaped.push_str(&rest[..i]);
+            let c = match rest.get(i + 1..i + 3)? {
+                "25" => '%',
+                "20" => ' ',
+                "09" => '\t',
+                "0A" => '\n',
+                "0D" => '\r',
+                _ => return None,
+            };
+            unescaped.push(c);
+            rest = &rest[i + 3..];
+        }
+        unescaped.push_str(rest);
+        Some(unescaped)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{escape, format_schedule, parse_schedule, unescape};
+        use crate::LearningRateSchedule;
+
+        #[test]
+        fn test_escape() {
+            for value in ["", "a", "a b", "100%", "%20", "line\nbreak\t"] {
+                assert_eq!(unescape(&escape(value)).unwrap(), value);
+            }
+            assert_eq!(unescape("%2"), None);
+        }
+
+        #[test]
+        fn test_schedule() {
+            for schedule in [
+                LearningRateSchedule::Constant,
+                LearningRateSchedule::Step {
+                    step_size: 5,
+                    gamma: 0.5,
+                },
+                LearningRateSchedule::Exponential { gamma: 0.9 },
+                LearningRateSchedule::Cosine {
+                    min_learning_rate: 0.001,
+                },
+                LearningRateSchedule::WarmupCosine {
+                    warmup: 3,
+                    min_learning_rate: 0.0,
+                },
+            ] {
+                assert_eq!(parse_schedule(&format_schedule(schedule)), Some(schedule));
+            }
+            assert_eq!(parse_schedule("Step,5"), None);
+            assert_eq!(parse_schedule("Exponential,0.9,1"), None);
+        }
+    }
+}
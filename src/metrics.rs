@@ -0,0 +1,103 @@
+use crate::GroupError;
+
+pub(crate) struct EntryError {
+    error: f64,
+    logloss: f64,
+    gkl: f64,
+    correct: bool,
+}
+
+impl EntryError {
+    pub(crate) fn new(actual: f32, predicted: f32) -> Self {
+        let r = actual as f64;
+        let z = predicted as f64;
+        let logloss = if actual > 0.0 {
+            softplus(-z)
+        } else {
+            softplus(z)
+        };
+        Self {
+            error: r - z,
+            logloss,
+            gkl: r * libm::log(r / z) - r + z,
+            correct: (actual > 0.0) == (predicted > 0.0),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct ErrorSum {
+    pub(crate) count: usize,
+    squared: f64,
+    absolute: f64,
+    logloss: f64,
+    gkl: f64,
+    correct: usize,
+}
+
+// all metrics match LIBMF and return zero for empty data
+impl ErrorSum {
+    pub(crate) fn add(&mut self, error: &EntryError) {
+        self.count += 1;
+        self.squared += error.error * error.error;
+        self.absolute += error.error.abs();
+        self.logloss += error.logloss;
+        self.gkl += error.gkl;
+        self.correct += error.correct as usize;
+    }
+
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        self.squared += other.squared;
+        self.absolute += other.absolute;
+        self.logloss += other.logloss;
+        self.gkl += other.gkl;
+        self.correct += other.correct;
+    }
+
+    pub(crate) fn rmse(&self) -> f64 {
+        libm::sqrt(self.mean(self.squared))
+    }
+
+    pub(crate) fn mae(&self) -> f64 {
+        self.mean(self.absolute)
+    }
+
+    pub(crate) fn gkl(&self) -> f64 {
+        self.mean(self.gkl)
+    }
+
+    pub(crate) fn logloss(&self) -> f64 {
+        self.mean(self.logloss)
+    }
+
+    pub(crate) fn accuracy(&self) -> f64 {
+        self.mean(self.correct as f64)
+    }
+
+    pub(crate) fn finish(&self) -> GroupError {
+        GroupError {
+            count: self.count,
+            rmse: self.rmse(),
+            mae: self.mae(),
+            logloss: self.logloss(),
+        }
+    }
+
+    fn mean(&self, sum: f64) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            sum / self.count as f64
+        }
+    }
+}
+
+// log(1 + exp(x)) without overflow
+fn softplus(x: f64) -> f64 {
+    if x > 0.0 {
+        x + libm::log1p(libm::exp(-x))
+    } else {
+        libm::log1p(libm::exp(x))
+    }
+}
@@ -1,12 +1,24 @@
+use crate::bias::Biases;
 use crate::bindings::*;
-use crate::{Error, Matrix, Params};
+use crate::observed::Observed;
+use crate::{
+    Error, ErrorBreakdown, EvaluationReport, Evaluator, FactorSummary, IoError, Loss, Matrix,
+    ModelMetadata, ModelSummary, Params,
+};
+use alloc::boxed::Box;
 use alloc::ffi::CString;
+use alloc::vec::Vec;
 use core::slice::Chunks;
 
 /// A model.
 #[derive(Debug)]
 pub struct Model {
     pub(crate) model: *mut MfModel,
+    pub(crate) metadata: ModelMetadata,
+    pub(crate) observed: Observed,
+    pub(crate) biases: Biases,
+    // factors allocated in Rust instead of LIBMF
+    pub(crate) owned_factors: Option<(Vec<f32>, Vec<f32>)>,
 }
 
 impl Model {
@@ -17,29 +29,195 @@ impl Model {
 
     /// Loads a model from a file.
     pub fn load(path: &str) -> Result<Self, Error> {
-        let path = CString::new(path).map_err(|_| Error::Io)?;
-        let model = unsafe { mf_load_model(path.as_ptr()) };
+        // get the reason from the operating system
+        #[cfg(feature = "std")]
+        let file =
+            std::fs::File::open(path).map_err(|e| Error::Io(IoError::read(path).with_source(e)))?;
+
+        let c_path = CString::new(path).map_err(|_| Error::Io(IoError::read(path)))?;
+        let model = unsafe { mf_load_model(c_path.as_ptr()) };
+        if model.is_null() {
+            // the file was already opened
+            #[cfg(feature = "std")]
+            return Err(Error::MalformedModel {
+                path: path.into(),
+                line: None,
+            });
+            #[cfg(not(feature = "std"))]
+            return Err(Error::Io(IoError::read(path)));
+        }
+        let mut model = Model::from_libmf(model)?;
+        model.observed = Observed::from_factors(
+            model.p_factors(),
+            model.q_factors(),
+            model.factors() as usize,
+        );
+        #[cfg(feature = "std")]
+        model.read_sections(file, path)?;
+        Ok(model)
+    }
+
+    // reads the sections appended after the factors in a single pass
+    #[cfg(feature = "std")]
+    fn read_sections(&mut self, file: std::fs::File, path: &str) -> Result<(), Error> {
+        use crate::bias::BiasReader;
+        use crate::observed::CountReader;
+        use std::io::{BufRead, BufReader};
+
+        let io_error = |e| Error::Io(IoError::read(path).with_source(e));
+        let mut metadata = ModelMetadata::default();
+        let mut counts = CountReader::new(path, self.rows(), self.columns());
+        let mut biases = BiasReader::new(path, self.rows(), self.columns());
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(io_error)?;
+            metadata.read_line(path, i + 1, &line)?;
+            counts.read_line(i + 1, &line)?;
+            biases.read_line(i + 1, &line)?;
+        }
+        self.metadata = metadata;
+        // files saved without counts use the factors
+        if let Some(observed) = counts.finish()? {
+            self.observed = observed;
+        }
+        self.biases = biases.finish()?;
+        Ok(())
+    }
+
+    // takes ownership of a model from LIBMF
+    pub(crate) fn from_libmf(model: *mut MfModel) -> Result<Self, Error> {
         if model.is_null() {
-            return Err(Error::Io);
+            return Err(Error::Unknown);
+        }
+        Ok(Model {
+            model,
+            metadata: ModelMetadata::default(),
+            observed: Observed::default(),
+            biases: Biases::default(),
+            owned_factors: None,
+        })
+    }
+
+    // for models trained in Rust
+    pub(crate) fn from_factors(
+        loss: Loss,
+        factors: i32,
+        bias: f32,
+        p: Vec<f32>,
+        q: Vec<f32>,
+    ) -> Self {
+        let k = factors.max(1) as usize;
+        let model = Box::new(MfModel {
+            fun: loss,
+            m: (p.len() / k) as i32,
+            n: (q.len() / k) as i32,
+            k: factors,
+            b: bias,
+            p: p.as_ptr(),
+            q: q.as_ptr(),
+        });
+        Model {
+            model: Box::into_raw(model),
+            metadata: ModelMetadata::default(),
+            observed: Observed::default(),
+            biases: Biases::default(),
+            owned_factors: Some((p, q)),
         }
-        Ok(Model { model })
     }
 
     /// Returns the predicted value for a row and column.
     pub fn predict(&self, row_index: i32, column_index: i32) -> f32 {
-        unsafe { mf_predict(self.model, row_index, column_index) }
+        let prediction = unsafe { mf_predict(self.model, row_index, column_index) };
+        if self.biases.is_empty() {
+            return prediction;
+        }
+        prediction + self.biases.predict(row_index, column_index)
+    }
+
+    /// Returns the predicted value for a row and column, or `None` if either had no training entries.
+    pub fn predict_checked(&self, row_index: i32, column_index: i32) -> Option<f32> {
+        if self.is_known_row(row_index) && self.is_known_column(column_index) {
+            Some(self.predict(row_index, column_index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether a row had entries in the training set.
+    ///
+    /// For models loaded from files saved without counts (including without the `std`
+    /// feature), this is whether the row has latent factors that are not NaN.
+    pub fn is_known_row(&self, row_index: i32) -> bool {
+        self.observed.is_known_row(row_index)
+    }
+
+    /// Returns whether a column had entries in the training set.
+    ///
+    /// For models loaded from files saved without counts (including without the `std`
+    /// feature), this is whether the column has latent factors that are not NaN.
+    pub fn is_known_column(&self, column_index: i32) -> bool {
+        self.observed.is_known_column(column_index)
+    }
+
+    /// Returns the number of training entries for each row.
+    ///
+    /// Counts are only saved with the `std` feature, so this returns `None` for models
+    /// loaded from files saved without them.
+    pub fn training_row_counts(&self) -> Option<&[usize]> {
+        self.observed.row_counts()
+    }
+
+    /// Returns the number of training entries for each column.
+    ///
+    /// Counts are only saved with the `std` feature, so this returns `None` for models
+    /// loaded from files saved without them.
+    pub fn training_column_counts(&self) -> Option<&[usize]> {
+        self.observed.column_counts()
     }
 
     /// Saves the model to a file.
+    ///
+    /// Metadata and training counts are only saved with the `std` feature. Models
+    /// with row or column biases require the `std` feature.
     pub fn save(&self, path: &str) -> Result<(), Error> {
-        let path = CString::new(path).map_err(|_| Error::Io)?;
-        let status = unsafe { mf_save_model(self.model, path.as_ptr()) };
+        // the biases would be lost
+        #[cfg(not(feature = "std"))]
+        if !self.biases.is_empty() {
+            return Err(Error::Io(IoError::write(path)));
+        }
+
+        // get the reason from the operating system
+        #[cfg(feature = "std")]
+        std::fs::File::create(path).map_err(|e| Error::Io(IoError::write(path).with_source(e)))?;
+
+        let c_path = CString::new(path).map_err(|_| Error::Io(IoError::write(path)))?;
+        let status = unsafe { mf_save_model(self.model, c_path.as_ptr()) };
         if status != 0 {
-            return Err(Error::Io);
+            return Err(Error::Io(IoError::write(path)));
         }
+        #[cfg(feature = "std")]
+        self.biases.append(path)?;
+        #[cfg(feature = "std")]
+        self.observed.append(path)?;
+        #[cfg(feature = "std")]
+        self.metadata.append(path)?;
         Ok(())
     }
 
+    /// Returns information about how the model was trained.
+    pub fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    /// Returns a mutable reference to the metadata (for adding tags).
+    pub fn metadata_mut(&mut self) -> &mut ModelMetadata {
+        &mut self.metadata
+    }
+
+    /// Returns the loss function.
+    pub fn loss(&self) -> Loss {
+        unsafe { (*self.model).fun }
+    }
+
     /// Returns the number of rows.
     pub fn rows(&self) -> i32 {
         unsafe { (*self.model).m }
@@ -60,6 +238,22 @@ impl Model {
         unsafe { (*self.model).b }
     }
 
+    /// Returns the bias for a row, if row biases were learned.
+    pub fn row_bias(&self, row_index: i32) -> Option<f32> {
+        if row_index < 0 {
+            return None;
+        }
+        self.biases.rows.get(row_index as usize).copied()
+    }
+
+    /// Returns the bias for a column, if column biases were learned.
+    pub fn column_bias(&self, column_index: i32) -> Option<f32> {
+        if column_index < 0 {
+            return None;
+        }
+        self.biases.columns.get(column_index as usize).copied()
+    }
+
     /// Returns the latent factors for rows.
     pub fn p_factors(&self) -> &[f32] {
         unsafe {
@@ -108,49 +302,145 @@ impl Model {
 
     /// Calculates RMSE (for real-valued MF).
     pub fn rmse(&self, data: &Matrix) -> Result<f64, Error> {
-        let prob = data.try_into()?;
-        Ok(unsafe { calc_rmse(&prob, self.model) })
+        self.metric(
+            data,
+            |evaluator| evaluator.rmse(data),
+            |prob, model| unsafe { calc_rmse(prob, model) },
+        )
     }
 
     /// Calculates MAE (for real-valued MF).
     pub fn mae(&self, data: &Matrix) -> Result<f64, Error> {
-        let prob = data.try_into()?;
-        Ok(unsafe { calc_mae(&prob, self.model) })
+        self.metric(
+            data,
+            |evaluator| evaluator.mae(data),
+            |prob, model| unsafe { calc_mae(prob, model) },
+        )
     }
 
     /// Calculates generalized KL-divergence (for non-negative real-valued MF).
     pub fn gkl(&self, data: &Matrix) -> Result<f64, Error> {
-        let prob = data.try_into()?;
-        Ok(unsafe { calc_gkl(&prob, self.model) })
+        self.metric(
+            data,
+            |evaluator| evaluator.gkl(data),
+            |prob, model| unsafe { calc_gkl(prob, model) },
+        )
     }
 
     /// Calculates logarithmic loss (for binary MF).
     pub fn logloss(&self, data: &Matrix) -> Result<f64, Error> {
-        let prob = data.try_into()?;
-        Ok(unsafe { calc_logloss(&prob, self.model) })
+        self.metric(
+            data,
+            |evaluator| evaluator.logloss(data),
+            |prob, model| unsafe { calc_logloss(prob, model) },
+        )
     }
 
     /// Calculates accuracy (for binary MF).
     pub fn accuracy(&self, data: &Matrix) -> Result<f64, Error> {
-        let prob = data.try_into()?;
-        Ok(unsafe { calc_accuracy(&prob, self.model) })
+        self.metric(
+            data,
+            |evaluator| evaluator.accuracy(data),
+            |prob, model| unsafe { calc_accuracy(prob, model) },
+        )
     }
 
     /// Calculates MPR (for one-class MF).
     pub fn mpr(&self, data: &Matrix, transpose: bool) -> Result<f64, Error> {
-        let prob = data.try_into()?;
-        Ok(unsafe { calc_mpr(&prob, self.model, transpose) })
+        self.metric(
+            data,
+            |evaluator| evaluator.mpr(data, transpose),
+            |prob, model| unsafe { calc_mpr(prob, model, transpose) },
+        )
     }
 
     /// Calculates AUC (for one-class MF).
     pub fn auc(&self, data: &Matrix, transpose: bool) -> Result<f64, Error> {
+        self.metric(
+            data,
+            |evaluator| evaluator.auc(data, transpose),
+            |prob, model| unsafe { calc_auc(prob, model, transpose) },
+        )
+    }
+
+    // LIBMF does not know about the biases, so models with biases use the evaluator
+    fn metric<E, L>(&self, data: &Matrix, evaluator: E, libmf: L) -> Result<f64, Error>
+    where
+        E: FnOnce(Evaluator<'_>) -> Result<f64, Error>,
+        L: FnOnce(&MfProblem, *const MfModel) -> f64,
+    {
+        if !self.biases.is_empty() {
+            return evaluator(self.evaluator()?);
+        }
         let prob = data.try_into()?;
-        Ok(unsafe { calc_auc(&prob, self.model, transpose) })
+        Ok(libmf(&prob, self.model))
+    }
+
+    /// Returns diagnostics for the latent factors.
+    pub fn summary(&self) -> ModelSummary {
+        let factors = self.factors() as usize;
+        ModelSummary {
+            p: FactorSummary::new(self.p_factors(), factors),
+            q: FactorSummary::new(self.q_factors(), factors),
+        }
+    }
+
+    /// Returns an evaluator that calculates metrics in Rust.
+    ///
+    /// Returns an error for loaded models with no factors.
+    pub fn evaluator(&self) -> Result<Evaluator<'_>, Error> {
+        let mut evaluator = Evaluator::new(
+            self.p_factors(),
+            self.q_factors(),
+            self.factors(),
+            self.bias(),
+            self.loss(),
+        )?;
+        evaluator.biases(&self.biases.rows, &self.biases.columns);
+        Ok(evaluator)
+    }
+
+    /// Calculates the metrics relevant to the loss function.
+    pub fn evaluate(&self, data: &Matrix) -> Result<EvaluationReport, Error> {
+        let loss = self.loss();
+        if !matches!(
+            loss,
+            Loss::OneClassRow | Loss::OneClassCol | Loss::OneClassL2
+        ) {
+            return self.evaluator()?.evaluate(data);
+        }
+
+        // ranking metrics use LIBMF, so the data is only converted here
+        let prob = data.try_into()?;
+        let transpose = matches!(loss, Loss::OneClassCol);
+        let mut report = EvaluationReport::new(loss, data.data.len());
+        report.mpr = Some(unsafe { calc_mpr(&prob, self.model, transpose) });
+        report.auc = Some(unsafe { calc_auc(&prob, self.model, transpose) });
+        Ok(report)
+    }
+
+    /// Calculates RMSE, MAE, and logarithmic loss by row, column, and number of training entries.
+    pub fn error_breakdown(
+        &self,
+        data: &Matrix,
+        train_set: &Matrix,
+    ) -> Result<ErrorBreakdown, Error> {
+        // check for invalid nodes
+        let _: MfProblem = data.try_into()?;
+        let _: MfProblem = train_set.try_into()?;
+        Ok(ErrorBreakdown::new(&data.data, &train_set.data, |u, v| {
+            self.predict(u, v)
+        }))
     }
 }
 
 impl Drop for Model {
     fn drop(&mut self) {
+        if self.owned_factors.is_some() {
+            // the factors are freed with the Vecs
+            drop(unsafe { Box::from_raw(self.model) });
+            return;
+        }
         unsafe { mf_destroy_model(&mut self.model) };
         assert!(self.model.is_null());
     }
@@ -158,7 +448,9 @@ impl Drop for Model {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Error, Loss, Matrix, Model};
+    use crate::{Error, Loss, Matrix, Model, ParameterError, Solver};
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
 
     fn generate_data() -> Matrix {
         let mut data = Matrix::new();
@@ -201,6 +493,20 @@ mod tests {
         assert_eq!(model.q(2), None);
     }
 
+    #[test]
+    fn test_summary() {
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+        let summary = model.summary();
+
+        assert_eq!(summary.p.count, 2);
+        assert_eq!(summary.q.count, 2);
+        assert_eq!(summary.p.non_finite, 0);
+        assert_eq!(summary.p.unseen_fraction, 0.0);
+        assert_eq!(summary.p.singular_values.len(), 8);
+        assert!(summary.p.max_norm >= summary.p.min_norm);
+    }
+
     #[test]
     fn test_fit_eval() {
         let data = generate_data();
@@ -230,7 +536,7 @@ mod tests {
             .fit_eval(&train_set, &eval_set);
         assert_eq!(
             result.unwrap_err(),
-            Error::Parameter("eval set cannot have extra rows for OneClassL2 loss")
+            Error::Parameter(ParameterError::EvalRows(1000001))
         );
     }
 
@@ -244,16 +550,39 @@ mod tests {
             .fit_eval(&train_set, &eval_set);
         assert_eq!(
             result.unwrap_err(),
-            Error::Parameter("eval set cannot have extra columns for OneClassL2 loss")
+            Error::Parameter(ParameterError::EvalColumns(1000001))
         );
     }
 
     #[test]
     fn test_cv() {
         let data = generate_data();
-        let avg_error = Model::params().quiet(true).cv(&data, 5).unwrap();
+        let result = Model::params().quiet(true).cv(&data, 5);
         // not enough data
-        assert!(avg_error.is_nan());
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Parameter(ParameterError::Folds(5))
+        );
+    }
+
+    #[test]
+    fn test_cv_enough_data() {
+        let mut data = Matrix::new();
+        for i in 0..20 {
+            data.push(i % 4, i % 5, (i % 3) as f32);
+        }
+        let avg_error = Model::params().quiet(true).cv(&data, 5).unwrap();
+        assert!(avg_error.is_finite());
+    }
+
+    #[test]
+    fn test_cv_one_fold() {
+        let data = generate_data();
+        let result = Model::params().quiet(true).cv(&data, 1);
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Parameter(ParameterError::Folds(1))
+        );
     }
 
     #[test]
@@ -322,6 +651,112 @@ mod tests {
         model.p_factors();
         model.q_factors();
         model.bias();
+
+        assert!(model.is_known_row(1));
+        assert!(model.is_known_column(1));
+        #[cfg(feature = "std")]
+        {
+            assert_eq!(model.training_row_counts(), Some([1, 2].as_slice()));
+            assert_eq!(model.training_column_counts(), Some([2, 1].as_slice()));
+        }
+        #[cfg(not(feature = "std"))]
+        assert_eq!(model.training_row_counts(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_save_load_counts() {
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+        let path = "target/model_counts.txt";
+        model.save(path).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+
+        // files saved without counts
+        let lines: std::vec::Vec<&str> = contents
+            .lines()
+            .filter(|line| !line.starts_with("rc") && !line.starts_with("cc"))
+            .collect();
+        std::fs::write(path, lines.join("\n") + "\n").unwrap();
+        let loaded = Model::load(path).unwrap();
+        assert_eq!(loaded.training_row_counts(), None);
+        assert!(loaded.is_known_row(1));
+
+        // missing the count for a column
+        let lines: std::vec::Vec<&str> = contents.lines().filter(|&line| line != "cc1 1").collect();
+        std::fs::write(path, lines.join("\n") + "\n").unwrap();
+        assert!(matches!(
+            Model::load(path).unwrap_err(),
+            Error::MalformedModel { .. }
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_load_incomplete_bias() {
+        let path = "target/model_incomplete_bias.txt";
+        let model = "f 0\nm 2\nn 1\nk 1\nb 0.5\np0 T 1\np1 T 1\nq0 T 2\n";
+        std::fs::write(path, std::format!("{}rb0 0.5\ncb0 0.25\n", model)).unwrap();
+        assert_eq!(
+            Model::load(path).unwrap_err(),
+            Error::MalformedModel {
+                path: path.into(),
+                line: Some(10)
+            }
+        );
+
+        // a model can have biases for only rows or columns
+        std::fs::write(path, std::format!("{}cb0 0.25\n", model)).unwrap();
+        let loaded = Model::load(path).unwrap();
+        assert_eq!(loaded.row_bias(0), None);
+        assert_eq!(loaded.column_bias(0), Some(0.25));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+
+        let metadata = model.metadata();
+        assert_eq!(metadata.params.as_ref().unwrap().get_factors(), 8);
+        assert_eq!(metadata.nnz, Some(3));
+        assert_eq!(metadata.version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+        assert!(metadata.tags.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_save_load_metadata() {
+        let data = generate_data();
+        let mut model = Model::params()
+            .factors(4)
+            .learning_rate(0.05)
+            .quiet(true)
+            .fit(&data)
+            .unwrap();
+        model
+            .metadata_mut()
+            .tags
+            .insert("dataset".into(), "ratings v2".into());
+        assert!(model.metadata().trained_at.is_some());
+        assert!(model.metadata().training_time.is_some());
+
+        let path = "target/model_metadata.txt";
+        model.save(path).unwrap();
+        let loaded = Model::load(path).unwrap();
+
+        assert_eq!(model.metadata(), loaded.metadata());
+        assert_eq!(loaded.factors(), 4);
+        assert_eq!(loaded.p_factors(), model.p_factors());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_load_without_metadata() {
+        let path = "target/model_no_metadata.txt";
+        std::fs::write(path, "f 0\nm 1\nn 1\nk 1\nb 0.5\np0 T 1\nq0 T 2\n").unwrap();
+        let model = Model::load(path).unwrap();
+        assert_eq!(model.metadata(), &crate::ModelMetadata::default());
     }
 
     #[test]
@@ -329,13 +764,64 @@ mod tests {
         let data = generate_data();
         let model = Model::params().quiet(true).fit(&data).unwrap();
         let result = model.save("missing/model.txt");
-        assert_eq!(result.unwrap_err(), Error::Io);
+        match result.unwrap_err() {
+            Error::Io(err) => {
+                assert_eq!(err.path(), "missing/model.txt");
+                assert!(err.is_write());
+                assert_eq!(err.to_string(), "cannot write file: missing/model.txt");
+                #[cfg(feature = "std")]
+                assert_eq!(err.io_error().unwrap().kind(), std::io::ErrorKind::NotFound);
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
     }
 
     #[test]
     fn test_load_missing() {
         let result = Model::load("missing.txt");
-        assert_eq!(result.unwrap_err(), Error::Io);
+        match result.unwrap_err() {
+            Error::Io(err) => {
+                assert_eq!(err.path(), "missing.txt");
+                assert!(!err.is_write());
+                assert_eq!(err.to_string(), "cannot read file: missing.txt");
+                #[cfg(feature = "std")]
+                assert_eq!(err.io_error().unwrap().kind(), std::io::ErrorKind::NotFound);
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_load_malformed_metadata() {
+        let path = "target/model_malformed.txt";
+        std::fs::write(
+            path,
+            "f 0\nm 1\nn 1\nk 1\nb 0.5\np0 T 1\nq0 T 2\nmeta nnz x\n",
+        )
+        .unwrap();
+        let result = Model::load(path);
+        assert_eq!(
+            result.unwrap_err(),
+            Error::MalformedModel {
+                path: path.into(),
+                line: Some(8)
+            }
+        );
+    }
+
+    #[test]
+    fn test_error_field() {
+        let data = generate_data();
+        let result = Model::params().lambda_q2(-1.0).fit(&data);
+        match result.unwrap_err() {
+            Error::Parameter(err) => {
+                assert_eq!(err, ParameterError::LambdaQ2(-1.0));
+                assert_eq!(err.field(), Some("lambda_q2"));
+                assert_eq!(err.to_string(), "lambda_q2 must be non-negative (got -1)");
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
     }
 
     #[test]
@@ -367,6 +853,253 @@ mod tests {
         assert!(model.auc(&empty, false).unwrap().is_nan());
     }
 
+    #[test]
+    fn test_evaluate() {
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+        let report = model.evaluate(&data).unwrap();
+
+        assert_eq!(report.loss, Loss::RealL2);
+        assert_eq!(report.count, 3);
+        assert!((report.rmse.unwrap() - model.rmse(&data).unwrap()).abs() < 1e-6);
+        assert!((report.mae.unwrap() - model.mae(&data).unwrap()).abs() < 1e-6);
+        assert_eq!(report.gkl, None);
+        assert_eq!(report.logloss, None);
+        assert_eq!(report.accuracy, None);
+        assert_eq!(report.mpr, None);
+        assert_eq!(report.auc, None);
+        assert!(report
+            .to_string()
+            .starts_with("loss: RealL2\ncount: 3\nrmse: "));
+    }
+
+    #[test]
+    fn test_evaluator_no_factors() {
+        let model = Model::from_factors(Loss::RealL2, 0, 0.0, Vec::new(), Vec::new());
+        assert_eq!(
+            model.evaluator().err(),
+            Some(Error::Parameter(ParameterError::Factors(0)))
+        );
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        assert!(model.evaluate(&data).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_evaluate_serde() {
+        use crate::EvaluationReport;
+
+        let data = generate_data();
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+        let report = model.evaluate(&data).unwrap();
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.starts_with("{\"loss\":\"RealL2\",\"count\":3,"));
+        let parsed: EvaluationReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(report, parsed);
+    }
+
+    #[test]
+    fn test_evaluate_one_class() {
+        let data = generate_data();
+        let model = Model::params()
+            .loss(Loss::OneClassRow)
+            .quiet(true)
+            .fit(&data)
+            .unwrap();
+        let report = model.evaluate(&data).unwrap();
+
+        assert_eq!(report.loss, Loss::OneClassRow);
+        assert_eq!(report.rmse, None);
+        assert_eq!(report.mpr, Some(model.mpr(&data, false).unwrap()));
+        assert_eq!(report.auc, Some(model.auc(&data, false).unwrap()));
+    }
+
+    #[test]
+    fn test_bias() {
+        let mut data = generate_data();
+        data.push(2, 1, 5.0);
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .row_bias(true)
+            .column_bias(true)
+            .fit(&data)
+            .unwrap();
+
+        assert!(model.row_bias(2).unwrap() > model.row_bias(0).unwrap());
+        assert!(model.column_bias(1).is_some());
+        assert_eq!(model.row_bias(3), None);
+        assert_eq!(model.row_bias(-1), None);
+        let params = model.metadata().params.as_ref().unwrap();
+        assert!(params.get_row_bias());
+
+        let evaluator = model.evaluator().unwrap();
+        assert_eq!(model.predict(2, 1), evaluator.predict(2, 1));
+        assert_eq!(model.rmse(&data).unwrap(), evaluator.rmse(&data).unwrap());
+        let report = model.evaluate(&data).unwrap();
+        assert!((report.rmse.unwrap() - model.rmse(&data).unwrap()).abs() < 1e-6);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_save_load_bias() {
+        let data = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .row_bias(true)
+            .column_bias(true)
+            .lambda_bias(0.5)
+            .fit(&data)
+            .unwrap();
+
+        let path = "target/bias.txt";
+        model.save(path).unwrap();
+        let loaded = Model::load(path).unwrap();
+        let params = loaded.metadata().params.as_ref().unwrap();
+        assert_eq!(params.get_lambda_bias(), 0.5);
+        assert_eq!(loaded.row_bias(1), model.row_bias(1));
+        assert_eq!(loaded.column_bias(1), model.column_bias(1));
+        assert_eq!(loaded.predict(1, 1), model.predict(1, 1));
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn test_save_bias_no_std() {
+        let data = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .row_bias(true)
+            .fit(&data)
+            .unwrap();
+        assert!(model.save("target/bias.txt").is_err());
+    }
+
+    #[test]
+    fn test_bias_row_only() {
+        let data = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::Als)
+            .row_bias(true)
+            .fit(&data)
+            .unwrap();
+        assert!(model.row_bias(1).is_some());
+        assert_eq!(model.column_bias(0), None);
+    }
+
+    #[test]
+    fn test_bias_loss() {
+        let data = generate_data();
+        for loss in [Loss::RealL1, Loss::OneClassRow] {
+            let result = Model::params().loss(loss).row_bias(true).fit(&data);
+            assert_eq!(
+                result.unwrap_err(),
+                Error::Parameter(ParameterError::BiasLoss(loss))
+            );
+        }
+    }
+
+    #[test]
+    fn test_bias_joint() {
+        // the rows and columns only differ by an offset, which the biases learn
+        let mut data = Matrix::new();
+        for u in 0..10 {
+            for v in 0..10 {
+                data.push(u, v, 1.0 + 0.2 * u as f32 + 0.1 * v as f32);
+            }
+        }
+        for solver in [Solver::RustSgd, Solver::Als] {
+            let model = Model::params()
+                .quiet(true)
+                .solver(solver)
+                .factors(2)
+                .lambda_bias(0.0)
+                .row_bias(true)
+                .column_bias(true)
+                .fit(&data)
+                .unwrap();
+            let row_bias = |u| model.row_bias(u).unwrap();
+            let column_bias = |v| model.column_bias(v).unwrap();
+            assert!(row_bias(9) - row_bias(0) > 1.0, "{:?}", solver);
+            assert!(column_bias(9) - column_bias(0) > 0.5, "{:?}", solver);
+            assert!(model.rmse(&data).unwrap() < 0.15, "{:?}", solver);
+        }
+    }
+
+    #[test]
+    fn test_lambda_bias() {
+        let mut data = generate_data();
+        data.push(2, 1, 5.0);
+        let fit = |lambda_bias| {
+            Model::params()
+                .quiet(true)
+                .solver(Solver::Als)
+                .lambda_bias(lambda_bias)
+                .row_bias(true)
+                .fit(&data)
+                .unwrap()
+                .row_bias(2)
+                .unwrap()
+        };
+        assert!(fit(10.0).abs() < fit(0.0).abs());
+
+        let result = Model::params().lambda_bias(-1.0).fit(&data);
+        match result.unwrap_err() {
+            Error::Parameter(err) => {
+                assert_eq!(err, ParameterError::LambdaBias(-1.0));
+                assert_eq!(err.field(), Some("lambda_bias"));
+                assert_eq!(err.to_string(), "lambda_bias must be non-negative (got -1)");
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_bias_solver() {
+        let data = generate_data();
+        let result = Model::params().row_bias(true).fit(&data);
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Parameter(ParameterError::BiasSolver(Solver::Sgd))
+        );
+    }
+
+    #[test]
+    fn test_bias_cv() {
+        let data = generate_data();
+        let result = Model::params()
+            .solver(Solver::RustSgd)
+            .column_bias(true)
+            .cv(&data, 2);
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Parameter(ParameterError::BiasCv)
+        );
+    }
+
+    #[test]
+    fn test_predict_checked() {
+        let mut data = generate_data();
+        data.push(3, 2, 1.0);
+        let model = Model::params().quiet(true).fit(&data).unwrap();
+
+        assert!(model.is_known_row(1));
+        assert!(!model.is_known_row(2));
+        assert!(!model.is_known_row(4));
+        assert!(!model.is_known_row(-1));
+        assert!(model.is_known_column(2));
+        assert_eq!(model.training_row_counts(), Some([1, 2, 0, 1].as_slice()));
+        assert_eq!(model.training_column_counts(), Some([2, 1, 1].as_slice()));
+
+        assert_eq!(model.predict_checked(1, 0), Some(model.predict(1, 0)));
+        assert_eq!(model.predict_checked(2, 0), None);
+        assert_eq!(model.predict_checked(1000, 1000), None);
+    }
+
     #[test]
     fn test_predict_out_of_range() {
         let data = generate_data();
@@ -377,7 +1110,10 @@ mod tests {
     #[test]
     fn test_fit_empty() {
         let result = Model::params().quiet(true).fit(&Matrix::new());
-        assert_eq!(result.unwrap_err(), Error::Parameter("no data"));
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Parameter(ParameterError::NoData)
+        );
     }
 
     #[test]
@@ -385,13 +1121,19 @@ mod tests {
         let result = Model::params()
             .quiet(true)
             .fit_eval(&Matrix::new(), &Matrix::new());
-        assert_eq!(result.unwrap_err(), Error::Parameter("no data"));
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Parameter(ParameterError::NoData)
+        );
     }
 
     #[test]
     fn test_cv_empty() {
         let result = Model::params().quiet(true).cv(&Matrix::new(), 5);
-        assert_eq!(result.unwrap_err(), Error::Parameter("no data"));
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Parameter(ParameterError::NoData)
+        );
     }
 
     #[test]
@@ -400,7 +1142,7 @@ mod tests {
         let result = Model::params().factors(0).fit(&data);
         assert_eq!(
             result.unwrap_err(),
-            Error::Parameter("number of factors must be greater than zero")
+            Error::Parameter(ParameterError::Factors(0))
         );
     }
 
@@ -410,7 +1152,7 @@ mod tests {
         let result = Model::params().factors(0).fit_eval(&data, &data);
         assert_eq!(
             result.unwrap_err(),
-            Error::Parameter("number of factors must be greater than zero")
+            Error::Parameter(ParameterError::Factors(0))
         );
     }
 
@@ -420,7 +1162,7 @@ mod tests {
         let result = Model::params().factors(0).cv(&data, 5);
         assert_eq!(
             result.unwrap_err(),
-            Error::Parameter("number of factors must be greater than zero")
+            Error::Parameter(ParameterError::Factors(0))
         );
     }
 }
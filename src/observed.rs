@@ -0,0 +1,246 @@
+use crate::bindings::MfNode;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// which rows and columns had entries in the training set
+#[derive(Debug, Default)]
+pub(crate) struct Observed {
+    rows: Bitmap,
+    columns: Bitmap,
+    // not known for models loaded from files saved without counts
+    counts: Option<(Vec<usize>, Vec<usize>)>,
+}
+
+impl Observed {
+    pub(crate) fn from_data(data: &[MfNode], rows: i32, columns: i32) -> Self {
+        let mut row_counts = vec![0; rows.max(0) as usize];
+        let mut column_counts = vec![0; columns.max(0) as usize];
+        for x in data {
+            // eval sets can extend the dimensions, but training sets cannot
+            if let Some(count) = row_counts.get_mut(x.u as usize) {
+                *count += 1;
+            }
+            if let Some(count) = column_counts.get_mut(x.v as usize) {
+                *count += 1;
+            }
+        }
+        Self::from_counts(row_counts, column_counts)
+    }
+
+    fn from_counts(row_counts: Vec<usize>, column_counts: Vec<usize>) -> Self {
+        Self {
+            rows: Bitmap::from_iter(row_counts.iter().map(|&c| c > 0)),
+            columns: Bitmap::from_iter(column_counts.iter().map(|&c| c > 0)),
+            counts: Some((row_counts, column_counts)),
+        }
+    }
+
+    // LIBMF sets the factors of rows and columns without entries to NaN
+    pub(crate) fn from_factors(p: &[f32], q: &[f32], k: usize) -> Self {
+        let seen = |v: &[f32]| !v.iter().all(|v| v.is_nan());
+        let (rows, columns) = if k > 0 {
+            (
+                Bitmap::from_iter(p.chunks(k).map(seen)),
+                Bitmap::from_iter(q.chunks(k).map(seen)),
+            )
+        } else {
+            Default::default()
+        };
+        Self {
+            rows,
+            columns,
+            counts: None,
+        }
+    }
+
+    pub(crate) fn is_known_row(&self, row_index: i32) -> bool {
+        self.rows.get(row_index)
+    }
+
+    pub(crate) fn is_known_column(&self, column_index: i32) -> bool {
+        self.columns.get(column_index)
+    }
+
+    pub(crate) fn row_counts(&self) -> Option<&[usize]> {
+        self.counts.as_ref().map(|c| c.0.as_slice())
+    }
+
+    pub(crate) fn column_counts(&self) -> Option<&[usize]> {
+        self.counts.as_ref().map(|c| c.1.as_slice())
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) use file::CountReader;
+
+#[cfg(feature = "std")]
+mod file {
+    use super::Observed;
+    use crate::{Error, IoError};
+    use alloc::vec::Vec;
+    use std::fs::OpenOptions;
+    use std::io::{BufWriter, Write};
+
+    // appended after the factors, like the biases
+    const ROW_PREFIX: &str = "rc";
+    const COLUMN_PREFIX: &str = "cc";
+
+    impl Observed {
+        pub(crate) fn append(&self, path: &str) -> Result<(), Error> {
+            let (row_counts, column_counts) = match &self.counts {
+                Some(counts) => counts,
+                None => return Ok(()),
+            };
+            let io_error = |e| Error::Io(IoError::write(path).with_source(e));
+            let file = OpenOptions::new()
+                .append(true)
+                .open(path)
+                .map_err(io_error)?;
+            let mut w = BufWriter::new(file);
+            for (prefix, counts) in [(ROW_PREFIX, row_counts), (COLUMN_PREFIX, column_counts)] {
+                for (i, c) in counts.iter().enumerate() {
+                    writeln!(w, "{}{} {}", prefix, i, c).map_err(io_error)?;
+                }
+            }
+            w.flush().map_err(io_error)
+        }
+    }
+
+    // reads the counts a line at a time, along with the other sections
+    pub(crate) struct CountReader<'a> {
+        path: &'a str,
+        rows: usize,
+        columns: usize,
+        row_counts: Vec<usize>,
+        column_counts: Vec<usize>,
+        last: usize,
+    }
+
+    impl<'a> CountReader<'a> {
+        pub(crate) fn new(path: &'a str, rows: i32, columns: i32) -> Self {
+            Self {
+                path,
+                rows: rows.max(0) as usize,
+                columns: columns.max(0) as usize,
+                row_counts: Vec::new(),
+                column_counts: Vec::new(),
+                last: 0,
+            }
+        }
+
+        pub(crate) fn read_line(&mut self, number: usize, line: &str) -> Result<(), Error> {
+            let malformed = || Error::MalformedModel {
+                path: self.path.into(),
+                line: Some(number),
+            };
+            let (counts, len, rest) = if let Some(rest) = line.strip_prefix(ROW_PREFIX) {
+                (&mut self.row_counts, self.rows, rest)
+            } else if let Some(rest) = line.strip_prefix(COLUMN_PREFIX) {
+                (&mut self.column_counts, self.columns, rest)
+            } else {
+                return Ok(());
+            };
+            let (index, count) = rest.split_once(' ').ok_or_else(malformed)?;
+            let index: usize = index.parse().map_err(|_| malformed())?;
+            let count: usize = count.parse().map_err(|_| malformed())?;
+            // written in order
+            if index != counts.len() || index >= len {
+                return Err(malformed());
+            }
+            counts.push(count);
+            self.last = number;
+            Ok(())
+        }
+
+        // returns `None` for files saved without counts
+        pub(crate) fn finish(self) -> Result<Option<Observed>, Error> {
+            if self.last == 0 {
+                return Ok(None);
+            }
+            // every row and column has a count
+            if self.row_counts.len() != self.rows || self.column_counts.len() != self.columns {
+                return Err(Error::MalformedModel {
+                    path: self.path.into(),
+                    line: Some(self.last),
+                });
+            }
+            Ok(Some(Observed::from_counts(
+                self.row_counts,
+                self.column_counts,
+            )))
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitmap {
+    fn from_iter<I: Iterator<Item = bool>>(bits: I) -> Self {
+        let mut bitmap = Bitmap::default();
+        for bit in bits {
+            if bitmap.len % 64 == 0 {
+                bitmap.words.push(0);
+            }
+            if bit {
+                bitmap.words[bitmap.len / 64] |= 1 << (bitmap.len % 64);
+            }
+            bitmap.len += 1;
+        }
+        bitmap
+    }
+
+    fn get(&self, index: i32) -> bool {
+        if index < 0 || index as usize >= self.len {
+            return false;
+        }
+        let index = index as usize;
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bitmap, Observed};
+    use crate::bindings::MfNode;
+
+    #[test]
+    fn test_bitmap() {
+        let bitmap = Bitmap::from_iter((0..130).map(|i| i % 3 == 0));
+        assert!(bitmap.get(0));
+        assert!(!bitmap.get(1));
+        assert!(bitmap.get(129));
+        assert!(!bitmap.get(130));
+        assert!(!bitmap.get(-1));
+    }
+
+    #[test]
+    fn test_from_data() {
+        let data = [
+            MfNode { u: 0, v: 1, r: 1.0 },
+            MfNode { u: 0, v: 2, r: 1.0 },
+            MfNode { u: 2, v: 2, r: 1.0 },
+        ];
+        let observed = Observed::from_data(&data, 4, 3);
+        assert_eq!(observed.row_counts(), Some([2, 0, 1, 0].as_slice()));
+        assert_eq!(observed.column_counts(), Some([0, 1, 2].as_slice()));
+        assert!(observed.is_known_row(2));
+        assert!(!observed.is_known_row(3));
+        assert!(!observed.is_known_column(0));
+        assert!(observed.is_known_column(1));
+    }
+
+    #[test]
+    fn test_from_factors() {
+        let p = [1.0, 2.0, f32::NAN, f32::NAN, 0.0, f32::NAN];
+        let observed = Observed::from_factors(&p, &[], 2);
+        assert!(observed.is_known_row(0));
+        assert!(!observed.is_known_row(1));
+        assert!(observed.is_known_row(2));
+        assert!(!observed.is_known_column(0));
+        assert_eq!(observed.row_counts(), None);
+    }
+}
@@ -1,16 +1,49 @@
+use crate::als::{self, Solver};
+use crate::bias::Biases;
 use crate::bindings::*;
-use crate::{Error, Loss, Matrix, Model};
+use crate::divergence::{self, DivergenceCheck};
+use crate::hybrid;
+use crate::metadata::Timer;
+use crate::observed::Observed;
+use crate::sgd;
+use crate::{
+    Error, HybridModel, LearningRateSchedule, Loss, Matrix, Model, ModelMetadata, ParameterError,
+};
+use alloc::vec::Vec;
+use core::fmt;
 
 /// A set of parameters.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "ParamsData", into = "ParamsData")
+)]
 pub struct Params {
     param: MfParameter,
+    divergence_check: DivergenceCheck,
+    divergence_retries: u32,
+    row_bias: bool,
+    column_bias: bool,
+    lambda_bias: f32,
+    solver: Solver,
+    learning_rate_schedule: LearningRateSchedule,
 }
 
 impl Params {
     pub(crate) fn new() -> Self {
         let mut param = unsafe { mf_get_default_param() };
         param.nr_bins = 25;
-        Self { param }
+        Self {
+            param,
+            divergence_check: DivergenceCheck::None,
+            divergence_retries: 0,
+            row_bias: false,
+            column_bias: false,
+            lambda_bias: 0.1,
+            solver: Solver::Sgd,
+            learning_rate_schedule: LearningRateSchedule::Constant,
+        }
     }
 
     /// Sets the loss function.
@@ -97,124 +130,1051 @@ impl Params {
         self
     }
 
+    /// Sets how to check for divergence during or after training.
+    pub fn divergence_check(&mut self, value: DivergenceCheck) -> &mut Self {
+        self.divergence_check = value;
+        self
+    }
+
+    /// Sets the number of times to retry with half the learning rate after divergence.
+    pub fn divergence_retries(&mut self, value: u32) -> &mut Self {
+        self.divergence_retries = value;
+        self
+    }
+
+    /// Sets whether to learn a bias term for each row (for `Loss::RealL2`).
+    ///
+    /// The biases are learned alongside the factors, so this requires `Solver::RustSgd`
+    /// or `Solver::Als`.
+    pub fn row_bias(&mut self, value: bool) -> &mut Self {
+        self.row_bias = value;
+        self
+    }
+
+    /// Sets whether to learn a bias term for each column (for `Loss::RealL2`).
+    ///
+    /// The biases are learned alongside the factors, so this requires `Solver::RustSgd`
+    /// or `Solver::Als`.
+    pub fn column_bias(&mut self, value: bool) -> &mut Self {
+        self.column_bias = value;
+        self
+    }
+
+    /// Sets the L2-regularization parameter for the row and column biases.
+    pub fn lambda_bias(&mut self, value: f32) -> &mut Self {
+        self.lambda_bias = value;
+        self
+    }
+
+    /// Sets the solver.
+    pub fn solver(&mut self, value: Solver) -> &mut Self {
+        self.solver = value;
+        self
+    }
+
+    /// Sets how the learning rate changes after each iteration.
+    ///
+    /// LIBMF uses a single learning rate and ALS does not use one, so any schedule
+    /// other than `LearningRateSchedule::Constant` requires `Solver::RustSgd`. Fitting
+    /// with `Solver::Sgd` (the default) or `Solver::Als` returns
+    /// `ParameterError::ScheduleSolver`.
+    pub fn learning_rate_schedule(&mut self, value: LearningRateSchedule) -> &mut Self {
+        self.learning_rate_schedule = value;
+        self
+    }
+
+    /// Returns the loss function.
+    pub fn get_loss(&self) -> Loss {
+        self.param.fun
+    }
+
+    /// Returns the number of latent factors.
+    pub fn get_factors(&self) -> i32 {
+        self.param.k
+    }
+
+    /// Returns the number of threads.
+    pub fn get_threads(&self) -> i32 {
+        self.param.nr_threads
+    }
+
+    /// Returns the number of bins.
+    pub fn get_bins(&self) -> i32 {
+        self.param.nr_bins
+    }
+
+    /// Returns the number of iterations.
+    pub fn get_iterations(&self) -> i32 {
+        self.param.nr_iters
+    }
+
+    /// Returns the L1-regularization parameter for P.
+    pub fn get_lambda_p1(&self) -> f32 {
+        self.param.lambda_p1
+    }
+
+    /// Returns the L2-regularization parameter for P.
+    pub fn get_lambda_p2(&self) -> f32 {
+        self.param.lambda_p2
+    }
+
+    /// Returns the L1-regularization parameter for Q.
+    pub fn get_lambda_q1(&self) -> f32 {
+        self.param.lambda_q1
+    }
+
+    /// Returns the L2-regularization parameter for Q.
+    pub fn get_lambda_q2(&self) -> f32 {
+        self.param.lambda_q2
+    }
+
+    /// Returns the learning rate.
+    pub fn get_learning_rate(&self) -> f32 {
+        self.param.eta
+    }
+
+    /// Returns the importance of negative entries.
+    pub fn get_alpha(&self) -> f32 {
+        self.param.alpha
+    }
+
+    /// Returns the desired value of negative entries.
+    pub fn get_c(&self) -> f32 {
+        self.param.c
+    }
+
+    /// Returns whether to perform non-negative MF (NMF).
+    pub fn get_nmf(&self) -> bool {
+        self.param.do_nmf
+    }
+
+    /// Returns whether to output to stdout.
+    pub fn get_quiet(&self) -> bool {
+        self.param.quiet
+    }
+
+    /// Returns how to check for divergence during or after training.
+    pub fn get_divergence_check(&self) -> DivergenceCheck {
+        self.divergence_check
+    }
+
+    /// Returns the number of times to retry with half the learning rate after divergence.
+    pub fn get_divergence_retries(&self) -> u32 {
+        self.divergence_retries
+    }
+
+    /// Returns whether to learn a bias term for each row.
+    pub fn get_row_bias(&self) -> bool {
+        self.row_bias
+    }
+
+    /// Returns whether to learn a bias term for each column.
+    pub fn get_column_bias(&self) -> bool {
+        self.column_bias
+    }
+
+    /// Returns the L2-regularization parameter for the row and column biases.
+    pub fn get_lambda_bias(&self) -> f32 {
+        self.lambda_bias
+    }
+
+    /// Returns the solver.
+    pub fn get_solver(&self) -> Solver {
+        self.solver
+    }
+
+    /// Returns how the learning rate changes after each iteration.
+    pub fn get_learning_rate_schedule(&self) -> LearningRateSchedule {
+        self.learning_rate_schedule
+    }
+
     /// Fits a model.
     pub fn fit(&self, data: &Matrix) -> Result<Model, Error> {
         // prevent "posix_memalign() invalid size value: 0" with Valgrind
         if data.is_empty() {
-            return Err(Error::Parameter("no data"));
+            return Err(ParameterError::NoData.into());
         }
 
-        let prob = data.try_into()?;
-        let param = self.build_param()?;
-        let model = unsafe { mf_train(&prob, param) };
-        if model.is_null() {
-            return Err(Error::Unknown);
+        let _: MfProblem = data.try_into()?;
+        self.build_param()?;
+        check_weights(data, self.solver)?;
+
+        if self.solver == Solver::Als {
+            return self.train(data, |param| als::train(data, None, &param, self));
+        }
+
+        if self.solver == Solver::RustSgd {
+            return self.train(data, |param| sgd::train(data, None, &param, self));
         }
-        Ok(Model { model })
+
+        let prob = data.try_into()?;
+        self.train(data, |param| {
+            Model::from_libmf(unsafe { mf_train(&prob, param) })
+        })
     }
 
     /// Fits a model and performs cross-validation.
     pub fn fit_eval(&self, train_set: &Matrix, eval_set: &Matrix) -> Result<Model, Error> {
         // prevent "posix_memalign() invalid size value: 0" with Valgrind
         if train_set.is_empty() || eval_set.is_empty() {
-            return Err(Error::Parameter("no data"));
+            return Err(ParameterError::NoData.into());
         }
 
+        let _: MfProblem = train_set.try_into()?;
+        let _: MfProblem = eval_set.try_into()?;
+        let param = self.build_param()?;
+        check_weights(train_set, self.solver)?;
         let tr: MfProblem = train_set.try_into()?;
         let va: MfProblem = eval_set.try_into()?;
-        let param = self.build_param()?;
 
         // LIBMF does not handle this case
         if matches!(param.fun, Loss::OneClassL2) {
             if va.m > tr.m {
-                return Err(Error::Parameter(
-                    "eval set cannot have extra rows for OneClassL2 loss",
-                ));
+                return Err(ParameterError::EvalRows(va.m).into());
             }
 
             if va.n > tr.n {
-                return Err(Error::Parameter(
-                    "eval set cannot have extra columns for OneClassL2 loss",
-                ));
+                return Err(ParameterError::EvalColumns(va.n).into());
             }
         }
 
-        let model = unsafe { mf_train_with_validation(&tr, &va, param) };
-        if model.is_null() {
-            return Err(Error::Unknown);
+        if self.solver == Solver::Als {
+            return self.train(train_set, |param| {
+                als::train(train_set, Some(eval_set), &param, self)
+            });
+        }
+
+        if self.solver == Solver::RustSgd {
+            return self.train(train_set, |param| {
+                sgd::train(train_set, Some(eval_set), &param, self)
+            });
         }
-        Ok(Model { model })
+
+        self.train(train_set, |param| {
+            Model::from_libmf(unsafe { mf_train_with_validation(&tr, &va, param) })
+        })
     }
 
     /// Performs cross-validation.
     pub fn cv(&self, data: &Matrix, folds: i32) -> Result<f64, Error> {
         // prevent "posix_memalign() invalid size value: 0" with Valgrind
         if data.is_empty() {
-            return Err(Error::Parameter("no data"));
+            return Err(ParameterError::NoData.into());
         }
 
         let prob = data.try_into()?;
         let param = self.build_param()?;
-        let avg_error = unsafe { mf_cross_validation(&prob, folds, param) };
-        // TODO update fork to differentiate between bad parameters and zero error
-        if avg_error == 0.0 {
-            return Err(Error::Unknown);
+
+        if !valid_folds(folds, data.data.len()) {
+            return Err(ParameterError::Folds(folds).into());
+        }
+
+        // LIBMF would ignore the biases and weights
+        if self.has_bias() {
+            return Err(ParameterError::BiasCv.into());
+        }
+        if data.weights().is_some() {
+            return Err(ParameterError::WeightCv.into());
+        }
+        if self.solver != Solver::Sgd {
+            return Err(ParameterError::SolverCv(self.solver).into());
+        }
+
+        let mut avg_error = 0.0;
+        let status = unsafe { mf_cross_validation_status(&prob, folds, param, &mut avg_error) };
+        if status != 0 {
+            return Err(Error::CrossValidation(status));
+        }
+        if self.divergence_check != DivergenceCheck::None && !avg_error.is_finite() {
+            return Err(Error::Diverged {
+                iteration: param.nr_iters,
+            });
         }
         Ok(avg_error)
     }
 
+    /// Fits a model with side features for rows and columns.
+    ///
+    /// Each entry of `row_features` is the value of a feature (column index) for a
+    /// row of `data` (row index), and `column_features` is the same for columns.
+    /// Either can be empty. Rows and columns with features but no entries, like new
+    /// items, are included in the model.
+    pub fn fit_hybrid(
+        &self,
+        data: &Matrix,
+        row_features: &Matrix,
+        column_features: &Matrix,
+    ) -> Result<HybridModel, Error> {
+        if data.is_empty() {
+            return Err(ParameterError::NoData.into());
+        }
+
+        let _: MfProblem = data.try_into()?;
+        let _: MfProblem = row_features.try_into()?;
+        let _: MfProblem = column_features.try_into()?;
+        let param = self.build_param()?;
+        // side features are always trained in Rust, which supports weights
+        check_weights(data, Solver::RustSgd)?;
+        if self.solver == Solver::Als {
+            return Err(ParameterError::AlsFeatures.into());
+        }
+        if matches!(
+            param.fun,
+            Loss::OneClassRow | Loss::OneClassCol | Loss::OneClassL2
+        ) {
+            return Err(ParameterError::FeatureLoss(param.fun).into());
+        }
+        for features in [row_features, column_features] {
+            if let Some(i) = features.data.iter().position(|x| !x.r.is_finite()) {
+                return Err(ParameterError::FeatureValue(i).into());
+            }
+        }
+
+        self.train_with_retries(data, |param| {
+            hybrid::train(data, row_features, column_features, &param, self)
+        })
+    }
+
+    fn has_bias(&self) -> bool {
+        self.row_bias || self.column_bias
+    }
+
+    fn has_schedule(&self) -> bool {
+        self.learning_rate_schedule != LearningRateSchedule::Constant
+    }
+
+    fn train<F: Fn(MfParameter) -> Result<Model, Error>>(
+        &self,
+        data: &Matrix,
+        train: F,
+    ) -> Result<Model, Error> {
+        let mut model = self.train_with_retries(data, train)?;
+        model.observed = Observed::from_data(&data.data, model.rows(), model.columns());
+        Ok(model)
+    }
+
+    // retries with a lower learning rate after divergence
+    fn train_with_retries<M: Trained, F: Fn(MfParameter) -> Result<M, Error>>(
+        &self,
+        data: &Matrix,
+        train: F,
+    ) -> Result<M, Error> {
+        let training_loss = |model: &M| {
+            let (p, q, factors) = model.latent_factors();
+            divergence::training_loss(p, q, factors, model.biases(), &data.data, self.param.fun)
+        };
+        let mut params = self.clone();
+        let mut retries = self.divergence_retries;
+        loop {
+            let timer = Timer::start();
+            // the Rust solvers return Diverged themselves with PerIteration
+            let result = train(params.param).and_then(|mut model| {
+                *model.metadata_mut() = ModelMetadata::new(&params, data.data.len(), timer);
+                let first_loss = || {
+                    let mut param = params.param;
+                    param.nr_iters = 1;
+                    train(param).map(|model| training_loss(&model))
+                };
+                params.check_divergence(|| training_loss(&model), first_loss)?;
+                Ok(model)
+            });
+            match result {
+                Err(Error::Diverged { .. }) if retries > 0 => {
+                    retries -= 1;
+                    params.param.eta /= 2.0;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // compares the training loss to the loss after the first iteration
+    fn check_divergence<L: FnOnce() -> f64, F: FnOnce() -> Result<f64, Error>>(
+        &self,
+        loss: L,
+        first_loss: F,
+    ) -> Result<(), Error> {
+        if self.divergence_check != DivergenceCheck::AfterTraining {
+            return Ok(());
+        }
+
+        // a non-finite loss has diverged, so skip training the extra model
+        let loss = loss();
+        let first_loss = if loss.is_finite() {
+            first_loss()?
+        } else {
+            f64::INFINITY
+        };
+        if divergence::diverged(loss, first_loss) {
+            return Err(Error::Diverged {
+                iteration: self.param.nr_iters,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the parameters against each other and against the data.
+    ///
+    /// Returns every problem found instead of stopping at the first one.
+    pub fn validate(&self, data: &Matrix) -> Result<(), Vec<Error>> {
+        let mut errors: Vec<Error> = self.param_errors().into_iter().map(Error::from).collect();
+        self.data_errors(data, &mut errors);
+        errors.extend(
+            weight_errors(data, self.solver)
+                .into_iter()
+                .map(Error::from),
+        );
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks the parameters for cross-validation.
+    ///
+    /// Returns every problem found instead of stopping at the first one.
+    pub fn validate_cv(&self, data: &Matrix, folds: i32) -> Result<(), Vec<Error>> {
+        let mut errors = match self.validate(data) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors,
+        };
+        if !valid_folds(folds, data.data.len()) {
+            errors.push(ParameterError::Folds(folds).into());
+        }
+        if self.has_bias() {
+            errors.push(ParameterError::BiasCv.into());
+        }
+        if data.weights().is_some() {
+            errors.push(ParameterError::WeightCv.into());
+        }
+        if self.solver != Solver::Sgd {
+            errors.push(ParameterError::SolverCv(self.solver).into());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     // check parameters in Rust for better error message
     fn build_param(&self) -> Result<MfParameter, Error> {
-        let param = self.param;
+        match self.param_errors().into_iter().next() {
+            Some(err) => Err(err.into()),
+            None => Ok(self.param),
+        }
+    }
+
+    fn param_errors(&self) -> Vec<ParameterError> {
+        let param = &self.param;
+        let mut errors = Vec::new();
 
         if param.k < 1 {
-            return Err(Error::Parameter(
-                "number of factors must be greater than zero",
-            ));
+            errors.push(ParameterError::Factors(param.k));
         }
 
         if param.nr_threads < 1 {
-            return Err(Error::Parameter(
-                "number of threads must be greater than zero",
-            ));
+            errors.push(ParameterError::Threads(param.nr_threads));
         }
 
         if param.nr_bins < 1 || param.nr_bins < param.nr_threads {
-            return Err(Error::Parameter(
-                "number of bins must be greater than number of threads",
-            ));
+            errors.push(ParameterError::Bins(param.nr_bins));
         }
 
         if param.nr_iters < 1 {
-            return Err(Error::Parameter(
-                "number of iterations must be greater than zero",
-            ));
+            errors.push(ParameterError::Iterations(param.nr_iters));
         }
 
-        if param.lambda_p1 < 0.0
-            || param.lambda_p2 < 0.0
-            || param.lambda_q1 < 0.0
-            || param.lambda_q2 < 0.0
-        {
-            return Err(Error::Parameter(
-                "regularization coefficient must be non-negative",
-            ));
+        if !non_negative(param.lambda_p1) {
+            errors.push(ParameterError::LambdaP1(param.lambda_p1));
+        }
+
+        if !non_negative(param.lambda_p2) {
+            errors.push(ParameterError::LambdaP2(param.lambda_p2));
         }
 
-        if param.eta <= 0.0 {
-            return Err(Error::Parameter("learning rate must be greater than zero"));
+        if !non_negative(param.lambda_q1) {
+            errors.push(ParameterError::LambdaQ1(param.lambda_q1));
+        }
+
+        if !non_negative(param.lambda_q2) {
+            errors.push(ParameterError::LambdaQ2(param.lambda_q2));
+        }
+
+        if !(param.eta > 0.0 && param.eta.is_finite()) {
+            errors.push(ParameterError::LearningRate(param.eta));
         }
 
         if matches!(param.fun, Loss::RealKL) && !param.do_nmf {
-            return Err(Error::Parameter(
-                "nmf must be set when using generalized KL-divergence",
+            errors.push(ParameterError::Nmf(param.fun));
+        }
+
+        // non-negative predictions cannot separate classes
+        if matches!(param.fun, Loss::BinaryL2 | Loss::BinaryL1) && param.do_nmf {
+            errors.push(ParameterError::NmfLoss(param.fun));
+        }
+
+        if !non_negative(param.alpha) {
+            errors.push(ParameterError::Alpha(param.alpha));
+        }
+
+        // the biases are learned with the squared error
+        if self.has_bias() && param.fun != Loss::RealL2 {
+            errors.push(ParameterError::BiasLoss(param.fun));
+        }
+
+        // LIBMF has no bias terms to update
+        if self.has_bias() && self.solver == Solver::Sgd {
+            errors.push(ParameterError::BiasSolver(self.solver));
+        }
+
+        if !non_negative(self.lambda_bias) {
+            errors.push(ParameterError::LambdaBias(self.lambda_bias));
+        }
+
+        if !param.c.is_finite() || (param.do_nmf && param.c < 0.0) {
+            errors.push(ParameterError::C(param.c));
+        }
+
+        let one_class = matches!(
+            param.fun,
+            Loss::OneClassRow | Loss::OneClassCol | Loss::OneClassL2
+        );
+        if self.solver == Solver::RustSgd && one_class {
+            errors.push(ParameterError::SolverLoss(self.solver, param.fun));
+        }
+
+        if self.solver == Solver::Als {
+            if !matches!(param.fun, Loss::RealL2 | Loss::OneClassL2) {
+                errors.push(ParameterError::SolverLoss(self.solver, param.fun));
+            }
+
+            if param.lambda_p1 > 0.0 || param.lambda_q1 > 0.0 {
+                errors.push(ParameterError::AlsL1);
+            }
+        }
+
+        if !self.learning_rate_schedule.is_valid() {
+            errors.push(ParameterError::LearningRateSchedule(
+                self.learning_rate_schedule,
             ));
         }
 
-        if param.alpha < 0.0 {
-            return Err(Error::Parameter("alpha must be a non-negative number"));
+        // LIBMF uses a single learning rate and ALS does not use one
+        if self.has_schedule() && self.solver != Solver::RustSgd {
+            errors.push(ParameterError::ScheduleSolver(self.solver));
+        }
+
+        // LIBMF does not report the loss of each iteration
+        if self.divergence_check == DivergenceCheck::PerIteration && self.solver == Solver::Sgd {
+            errors.push(ParameterError::DivergenceSolver(self.solver));
+        }
+
+        errors
+    }
+
+    fn data_errors(&self, data: &Matrix, errors: &mut Vec<Error>) {
+        if data.is_empty() {
+            errors.push(ParameterError::NoData.into());
+            return;
+        }
+
+        if let Err(err) = MfProblem::try_from(data) {
+            errors.push(err);
+        }
+
+        let fun = self.param.fun;
+        let binary = matches!(fun, Loss::BinaryLog | Loss::BinaryL2 | Loss::BinaryL1);
+        let non_negative = matches!(fun, Loss::RealKL);
+
+        // only report the first entry for each problem
+        let mut value_error = None;
+        let mut label_error = None;
+        let mut negative_error = None;
+        for (i, x) in data.data.iter().enumerate() {
+            if !x.r.is_finite() {
+                value_error.get_or_insert(ParameterError::Value(i));
+            } else if binary && x.r != 1.0 && x.r != -1.0 {
+                label_error.get_or_insert(ParameterError::Label(i));
+            } else if non_negative && x.r < 0.0 {
+                negative_error.get_or_insert(ParameterError::NegativeValue(i));
+            }
+        }
+        errors.extend(
+            [value_error, label_error, negative_error]
+                .into_iter()
+                .flatten()
+                .map(Error::from),
+        );
+    }
+}
+
+// a trained model, for checking divergence
+trait Trained {
+    fn latent_factors(&self) -> (&[f32], &[f32], usize);
+    fn biases(&self) -> &Biases;
+    fn metadata_mut(&mut self) -> &mut ModelMetadata;
+}
+
+impl Trained for Model {
+    fn latent_factors(&self) -> (&[f32], &[f32], usize) {
+        (self.p_factors(), self.q_factors(), self.factors() as usize)
+    }
+
+    fn biases(&self) -> &Biases {
+        &self.biases
+    }
+
+    fn metadata_mut(&mut self) -> &mut ModelMetadata {
+        &mut self.metadata
+    }
+}
+
+impl Trained for HybridModel {
+    fn latent_factors(&self) -> (&[f32], &[f32], usize) {
+        (self.p_factors(), self.q_factors(), self.factors() as usize)
+    }
+
+    fn biases(&self) -> &Biases {
+        &self.biases
+    }
+
+    fn metadata_mut(&mut self) -> &mut ModelMetadata {
+        &mut self.metadata
+    }
+}
+
+fn check_weights(data: &Matrix, solver: Solver) -> Result<(), Error> {
+    match weight_errors(data, solver).into_iter().next() {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+fn weight_errors(data: &Matrix, solver: Solver) -> Vec<ParameterError> {
+    let mut errors = Vec::new();
+    if let Some(weights) = data.weights() {
+        // LIBMF does not support weights
+        if solver == Solver::Sgd {
+            errors.push(ParameterError::WeightSolver(solver));
+        }
+        if let Some(i) = weights.iter().position(|&w| !non_negative(w)) {
+            errors.push(ParameterError::Weight(i));
+        }
+    }
+    errors
+}
+
+// also rejects NaN and infinity
+fn non_negative(value: f32) -> bool {
+    value >= 0.0 && value.is_finite()
+}
+
+// each fold needs at least one entry
+fn valid_folds(folds: i32, nnz: usize) -> bool {
+    folds >= 2 && folds as usize <= nnz
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Params")
+            .field("loss", &self.get_loss())
+            .field("factors", &self.get_factors())
+            .field("threads", &self.get_threads())
+            .field("bins", &self.get_bins())
+            .field("iterations", &self.get_iterations())
+            .field("lambda_p1", &self.get_lambda_p1())
+            .field("lambda_p2", &self.get_lambda_p2())
+            .field("lambda_q1", &self.get_lambda_q1())
+            .field("lambda_q2", &self.get_lambda_q2())
+            .field("learning_rate", &self.get_learning_rate())
+            .field("alpha", &self.get_alpha())
+            .field("c", &self.get_c())
+            .field("nmf", &self.get_nmf())
+            .field("quiet", &self.get_quiet())
+            .field("divergence_check", &self.get_divergence_check())
+            .field("divergence_retries", &self.get_divergence_retries())
+            .field("row_bias", &self.get_row_bias())
+            .field("column_bias", &self.get_column_bias())
+            .field("lambda_bias", &self.get_lambda_bias())
+            .field("solver", &self.get_solver())
+            .field("learning_rate_schedule", &self.get_learning_rate_schedule())
+            .finish()
+    }
+}
+
+// serialize by name instead of LIBMF field
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ParamsData {
+    loss: Loss,
+    factors: i32,
+    threads: i32,
+    bins: i32,
+    iterations: i32,
+    lambda_p1: f32,
+    lambda_p2: f32,
+    lambda_q1: f32,
+    lambda_q2: f32,
+    learning_rate: f32,
+    alpha: f32,
+    c: f32,
+    nmf: bool,
+    quiet: bool,
+    divergence_check: DivergenceCheck,
+    divergence_retries: u32,
+    row_bias: bool,
+    column_bias: bool,
+    lambda_bias: f32,
+    solver: Solver,
+    learning_rate_schedule: LearningRateSchedule,
+}
+
+#[cfg(feature = "serde")]
+impl Default for ParamsData {
+    fn default() -> Self {
+        Params::new().into()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Params> for ParamsData {
+    fn from(params: Params) -> Self {
+        Self {
+            loss: params.get_loss(),
+            factors: params.get_factors(),
+            threads: params.get_threads(),
+            bins: params.get_bins(),
+            iterations: params.get_iterations(),
+            lambda_p1: params.get_lambda_p1(),
+            lambda_p2: params.get_lambda_p2(),
+            lambda_q1: params.get_lambda_q1(),
+            lambda_q2: params.get_lambda_q2(),
+            learning_rate: params.get_learning_rate(),
+            alpha: params.get_alpha(),
+            c: params.get_c(),
+            nmf: params.get_nmf(),
+            quiet: params.get_quiet(),
+            divergence_check: params.get_divergence_check(),
+            divergence_retries: params.get_divergence_retries(),
+            row_bias: params.get_row_bias(),
+            column_bias: params.get_column_bias(),
+            lambda_bias: params.get_lambda_bias(),
+            solver: params.get_solver(),
+            learning_rate_schedule: params.get_learning_rate_schedule(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ParamsData> for Params {
+    fn from(data: ParamsData) -> Self {
+        let mut params = Params::new();
+        params
+            .loss(data.loss)
+            .factors(data.factors)
+            .threads(data.threads)
+            .bins(data.bins)
+            .iterations(data.iterations)
+            .lambda_p1(data.lambda_p1)
+            .lambda_p2(data.lambda_p2)
+            .lambda_q1(data.lambda_q1)
+            .lambda_q2(data.lambda_q2)
+            .learning_rate(data.learning_rate)
+            .alpha(data.alpha)
+            .c(data.c)
+            .nmf(data.nmf)
+            .quiet(data.quiet)
+            .divergence_check(data.divergence_check)
+            .divergence_retries(data.divergence_retries)
+            .row_bias(data.row_bias)
+            .column_bias(data.column_bias)
+            .lambda_bias(data.lambda_bias)
+            .solver(data.solver)
+            .learning_rate_schedule(data.learning_rate_schedule);
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DivergenceCheck, Error, LearningRateSchedule, Loss, Matrix, Model, ParameterError, Solver,
+    };
+
+    fn generate_data() -> Matrix {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 0, 2.0);
+        data.push(1, 1, 1.0);
+        data
+    }
+
+    #[test]
+    fn test_getters() {
+        let mut params = Model::params();
+        params
+            .loss(Loss::RealKL)
+            .factors(4)
+            .learning_rate(0.05)
+            .nmf(true);
+
+        assert_eq!(params.get_loss(), Loss::RealKL);
+        assert_eq!(params.get_factors(), 4);
+        assert_eq!(params.get_bins(), 25);
+        assert_eq!(params.get_learning_rate(), 0.05);
+        assert!(params.get_nmf());
+        assert!(!params.get_quiet());
+        assert_eq!(params.get_lambda_bias(), 0.1);
+        assert_eq!(params.get_solver(), Solver::Sgd);
+
+        let copy = params.clone();
+        assert_eq!(params, copy);
+        params.factors(8);
+        assert_ne!(params, copy);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 1, -1.0);
+
+        let mut params = Model::params();
+        params.loss(Loss::BinaryL1);
+        assert!(params.validate(&data).is_ok());
+        assert!(params.validate_cv(&data, 2).is_ok());
+
+        params.factors(0).learning_rate(f32::NAN).nmf(true);
+        data.push(2, 2, 0.5);
+        data.push(3, -1, f32::INFINITY);
+
+        let errors = params.validate_cv(&data, 5).unwrap_err();
+        let expected = [
+            Error::Parameter(ParameterError::Factors(0)),
+            Error::Parameter(ParameterError::NmfLoss(Loss::BinaryL1)),
+            Error::Node(3),
+            Error::Parameter(ParameterError::Value(3)),
+            Error::Parameter(ParameterError::Label(2)),
+            Error::Parameter(ParameterError::Folds(5)),
+        ];
+        assert_eq!(errors.len(), expected.len() + 1);
+        assert!(matches!(
+            errors[1],
+            Error::Parameter(ParameterError::LearningRate(_))
+        ));
+        for err in expected {
+            assert!(errors.contains(&err), "missing {:?}", err);
         }
+    }
+
+    #[test]
+    fn test_validate_empty() {
+        let errors = Model::params().validate(&Matrix::new()).unwrap_err();
+        assert_eq!(errors, [Error::Parameter(ParameterError::NoData)]);
+    }
+
+    #[test]
+    fn test_validate_real_kl() {
+        let mut data = Matrix::new();
+        data.push(0, 0, -1.0);
+
+        let mut params = Model::params();
+        params.loss(Loss::RealKL);
+        let errors = params.validate(&data).unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                Error::Parameter(ParameterError::Nmf(Loss::RealKL)),
+                Error::Parameter(ParameterError::NegativeValue(0))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fit_nan() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        let result = Model::params().lambda_p2(f32::NAN).fit(&data);
+        assert!(matches!(
+            result.unwrap_err(),
+            Error::Parameter(ParameterError::LambdaP2(_))
+        ));
+    }
+
+    #[test]
+    fn test_fit_diverged() {
+        let data = generate_data();
+        let mut params = Model::params();
+        params
+            .quiet(true)
+            .learning_rate(1e4)
+            .divergence_check(DivergenceCheck::AfterTraining);
+
+        let result = params.fit(&data);
+        assert_eq!(result.err(), Some(Error::Diverged { iteration: 20 }));
+
+        let result = params.fit_eval(&data, &data);
+        assert_eq!(result.err(), Some(Error::Diverged { iteration: 20 }));
+
+        let result = params.cv(&data, 2);
+        assert_eq!(result.unwrap_err(), Error::Diverged { iteration: 20 });
+    }
+
+    #[test]
+    fn test_fit_diverged_per_iteration() {
+        let data = generate_data();
+        let mut params = Model::params();
+        params
+            .quiet(true)
+            .learning_rate(1e4)
+            .divergence_check(DivergenceCheck::PerIteration);
+
+        let result = params.fit(&data);
+        assert_eq!(
+            result.err(),
+            Some(ParameterError::DivergenceSolver(Solver::Sgd).into())
+        );
+
+        params.solver(Solver::RustSgd);
+        let iteration = match params.fit(&data) {
+            Err(Error::Diverged { iteration }) => iteration,
+            result => panic!("unexpected result: {:?}", result.map(|_| ())),
+        };
+        assert!(iteration > 1 && iteration < 20);
+        let result = params.fit_eval(&data, &data);
+        assert_eq!(result.err(), Some(Error::Diverged { iteration }));
+
+        let model = params.divergence_retries(20).fit(&data).unwrap();
+        assert!(model.p_factors().iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn test_fit_diverged_no_check() {
+        let data = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .learning_rate(1e4)
+            .fit(&data)
+            .unwrap();
+        assert_eq!(
+            model
+                .metadata()
+                .params
+                .as_ref()
+                .unwrap()
+                .get_learning_rate(),
+            1e4
+        );
+    }
+
+    #[test]
+    fn test_fit_diverged_retries() {
+        let data = generate_data();
+        let mut params = Model::params();
+        params
+            .quiet(true)
+            .learning_rate(1e4)
+            .divergence_check(DivergenceCheck::AfterTraining)
+            .divergence_retries(20);
+        let model = params.fit(&data).unwrap();
+        assert!(model.p_factors().iter().all(|v| v.is_finite()));
+        let learning_rate = model
+            .metadata()
+            .params
+            .as_ref()
+            .unwrap()
+            .get_learning_rate();
+        assert!(learning_rate < 1e4);
+
+        // a model that does not diverge keeps the learning rate
+        let model = params.learning_rate(0.1).fit(&data).unwrap();
+        assert_eq!(
+            model
+                .metadata()
+                .params
+                .as_ref()
+                .unwrap()
+                .get_learning_rate(),
+            0.1
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut params = Model::params();
+        params.loss(Loss::OneClassL2).factors(4).quiet(true);
+
+        let json = serde_json::to_string(&params).unwrap();
+        assert!(json.starts_with("{\"loss\":\"OneClassL2\",\"factors\":4,"));
+        assert_eq!(params, serde_json::from_str(&json).unwrap());
+
+        let params: crate::Params =
+            serde_json::from_str("{\"loss\":\"BinaryLog\",\"iterations\":5}").unwrap();
+        assert_eq!(params.get_loss(), Loss::BinaryLog);
+        assert_eq!(params.get_iterations(), 5);
+        assert_eq!(params.get_factors(), 8);
+
+        assert!(serde_json::from_str::<crate::Params>("{\"unknown\":1}").is_err());
+    }
+
+    #[test]
+    fn test_learning_rate_schedule_errors() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(1, 1, 1.0);
+        let mut params = Model::params();
+        params.learning_rate_schedule(LearningRateSchedule::Exponential { gamma: 0.9 });
+
+        let result = params.fit(&data);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::ScheduleSolver(Solver::Sgd).into()
+        );
+
+        let result = params.cv(&data, 2);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::ScheduleSolver(Solver::Sgd).into()
+        );
+
+        let result = params.clone().solver(Solver::Als).fit(&data);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::ScheduleSolver(Solver::Als).into()
+        );
+
+        params.solver(Solver::RustSgd);
+        let result = params.clone().loss(Loss::OneClassL2).fit(&data);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::SolverLoss(Solver::RustSgd, Loss::OneClassL2).into()
+        );
 
-        Ok(param)
+        let schedule = LearningRateSchedule::Step {
+            step_size: 0,
+            gamma: 0.5,
+        };
+        let errors = params
+            .learning_rate_schedule(schedule)
+            .validate(&data)
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            [ParameterError::LearningRateSchedule(schedule).into()]
+        );
+        assert_eq!(
+            ParameterError::LearningRateSchedule(schedule).field(),
+            Some("learning_rate_schedule")
+        );
     }
 }
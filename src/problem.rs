@@ -1,5 +1,5 @@
 use crate::bindings::*;
-use crate::{Error, Matrix};
+use crate::{Error, Matrix, ParameterError};
 use core::ffi::c_int;
 
 impl TryFrom<&Matrix> for MfProblem {
@@ -25,7 +25,7 @@ impl TryFrom<&Matrix> for MfProblem {
         let nnz = data
             .len()
             .try_into()
-            .map_err(|_| Error::Parameter("too much data"))?;
+            .map_err(|_| ParameterError::TooMuchData)?;
 
         Ok(MfProblem {
             m: umax + 1,
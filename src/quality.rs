@@ -0,0 +1,257 @@
+use crate::bindings::MfNode;
+use alloc::vec::Vec;
+use core::ffi::c_int;
+
+/// A report of data quality problems in a matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationReport {
+    /// The number of entries.
+    pub len: usize,
+    /// The number of rows (largest valid row index plus one).
+    pub rows: usize,
+    /// The number of columns (largest valid column index plus one).
+    pub columns: usize,
+    /// The number of entries with a negative or too large row or column index.
+    pub invalid_indices: usize,
+    /// The number of entries with a NaN value.
+    pub nan_values: usize,
+    /// The number of entries with an infinite value.
+    pub infinite_values: usize,
+    /// The number of entries with the same row and column as an earlier entry.
+    pub duplicates: usize,
+    /// The number of rows without entries.
+    pub empty_rows: usize,
+    /// The number of columns without entries.
+    pub empty_columns: usize,
+    /// The smallest finite value.
+    pub min_value: Option<f32>,
+    /// The largest finite value.
+    pub max_value: Option<f32>,
+    /// The fraction of row and column pairs with an entry.
+    pub density: f64,
+}
+
+impl ValidationReport {
+    pub(crate) fn new(data: &[MfNode]) -> Self {
+        let mut report = ValidationReport {
+            len: data.len(),
+            rows: 0,
+            columns: 0,
+            invalid_indices: 0,
+            nan_values: 0,
+            infinite_values: 0,
+            duplicates: 0,
+            empty_rows: 0,
+            empty_columns: 0,
+            min_value: None,
+            max_value: None,
+            density: 0.0,
+        };
+
+        let mut pairs = Vec::with_capacity(data.len());
+        for x in data {
+            if x.r.is_nan() {
+                report.nan_values += 1;
+            } else if x.r.is_infinite() {
+                report.infinite_values += 1;
+            } else {
+                report.min_value = Some(report.min_value.map_or(x.r, |v| v.min(x.r)));
+                report.max_value = Some(report.max_value.map_or(x.r, |v| v.max(x.r)));
+            }
+
+            if valid_index(x.u) && valid_index(x.v) {
+                pairs.push((x.u, x.v));
+            } else {
+                report.invalid_indices += 1;
+            }
+        }
+
+        pairs.sort_unstable();
+        let before = pairs.len();
+        pairs.dedup();
+        report.duplicates = before - pairs.len();
+
+        let mut rows: Vec<c_int> = pairs.iter().map(|x| x.0).collect();
+        rows.dedup();
+        let mut columns: Vec<c_int> = pairs.iter().map(|x| x.1).collect();
+        columns.sort_unstable();
+        columns.dedup();
+
+        report.rows = rows.last().map_or(0, |&u| u as usize + 1);
+        report.columns = columns.last().map_or(0, |&v| v as usize + 1);
+        report.empty_rows = report.rows - rows.len();
+        report.empty_columns = report.columns - columns.len();
+        if !pairs.is_empty() {
+            report.density = pairs.len() as f64 / (report.rows as f64 * report.columns as f64);
+        }
+
+        report
+    }
+
+    /// Returns whether there are no invalid indices, non-finite values, or duplicates.
+    pub fn is_valid(&self) -> bool {
+        self.invalid_indices == 0
+            && self.nan_values == 0
+            && self.infinite_values == 0
+            && self.duplicates == 0
+    }
+}
+
+/// How to handle entries with the same row and column.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DedupPolicy {
+    /// Keep the first entry pushed.
+    First,
+    /// Keep the last entry pushed.
+    Last,
+    /// Merge the entries into one with the sum of values.
+    Sum,
+    /// Merge the entries into one with the mean of values.
+    Mean,
+    /// Merge the entries into one with the largest value.
+    Max,
+    /// Merge the entries into one with the number of entries.
+    Count,
+    /// Drop all of the entries.
+    DropAll,
+}
+
+// same check as MfProblem
+pub(crate) fn valid_index(index: c_int) -> bool {
+    index >= 0 && index != c_int::MAX
+}
+
+// aggregates a group of entries with the same row and column (in the order pushed),
+// returning the position of the entry to keep, the value, and the weight
+fn aggregate<V: Fn(usize) -> f32, W: Fn(usize) -> f32>(
+    len: usize,
+    value: V,
+    weight: W,
+    policy: DedupPolicy,
+) -> Option<(usize, f32, f32)> {
+    let mean_weight = || (0..len).map(|i| weight(i) as f64).sum::<f64>() / len as f64;
+    let sum = || (0..len).map(|i| value(i) as f64).sum::<f64>();
+    let (r, w) = match policy {
+        DedupPolicy::First => return Some((0, value(0), weight(0))),
+        DedupPolicy::Last => return Some((len - 1, value(len - 1), weight(len - 1))),
+        DedupPolicy::Sum => (sum(), mean_weight()),
+        DedupPolicy::Mean => (sum() / len as f64, mean_weight()),
+        DedupPolicy::Max => (
+            (0..len)
+                .map(|i| value(i) as f64)
+                .fold(f64::NEG_INFINITY, f64::max),
+            mean_weight(),
+        ),
+        DedupPolicy::Count => (len as f64, mean_weight()),
+        DedupPolicy::DropAll if len > 1 => return None,
+        DedupPolicy::DropAll => return Some((0, value(0), weight(0))),
+    };
+    Some((0, r as f32, w as f32))
+}
+
+// weights are empty when every entry has a weight of one
+pub(crate) fn sanitize(data: &mut Vec<MfNode>, weights: &mut Vec<f32>, policy: DedupPolicy) {
+    let weighted = !weights.is_empty();
+    let weight = |i: usize| if weighted { weights[i] } else { 1.0 };
+
+    // stable sort so entries in each group stay in the order pushed
+    let mut order: Vec<usize> = (0..data.len())
+        .filter(|&i| data[i].r.is_finite() && valid_index(data[i].u) && valid_index(data[i].v))
+        .collect();
+    order.sort_by_key(|&i| (data[i].u, data[i].v));
+
+    let mut keep = Vec::with_capacity(order.len());
+    for group in runs(&order, |&i| (data[i].u, data[i].v)) {
+        let merged = aggregate(
+            group.len(),
+            |i| data[group[i]].r,
+            |i| weight(group[i]),
+            policy,
+        );
+        if let Some((i, r, w)) = merged {
+            keep.push((group[i], r, w));
+        }
+    }
+
+    // restore the original order
+    keep.sort_unstable_by_key(|x| x.0);
+    let mut sanitized = Vec::with_capacity(keep.len());
+    let mut sanitized_weights = Vec::new();
+    for (i, r, w) in keep {
+        sanitized.push(MfNode {
+            u: data[i].u,
+            v: data[i].v,
+            r,
+        });
+        if weighted {
+            sanitized_weights.push(w);
+        }
+    }
+    *data = sanitized;
+    *weights = sanitized_weights;
+}
+
+// weights are empty when every entry has a weight of one
+pub(crate) fn dedup(data: &mut Vec<MfNode>, weights: &mut Vec<f32>, policy: DedupPolicy) {
+    let weighted = !weights.is_empty();
+
+    // stable sort so entries in each group stay in the order pushed
+    if weighted {
+        let mut order: Vec<usize> = (0..data.len()).collect();
+        order.sort_by_key(|&i| (data[i].u, data[i].v));
+        *weights = order.iter().map(|&i| weights[i]).collect();
+        *data = order
+            .iter()
+            .map(|&i| MfNode {
+                u: data[i].u,
+                v: data[i].v,
+                r: data[i].r,
+            })
+            .collect();
+    } else {
+        data.sort_by_key(|x| (x.u, x.v));
+    }
+
+    // aggregate in place to avoid extra memory
+    let len = data.len();
+    let mut write = 0;
+    let mut read = 0;
+    while read < len {
+        let (u, v) = (data[read].u, data[read].v);
+        let mut end = read;
+        while end < len && data[end].u == u && data[end].v == v {
+            end += 1;
+        }
+
+        let merged = aggregate(
+            end - read,
+            |i| data[read + i].r,
+            |i| if weighted { weights[read + i] } else { 1.0 },
+            policy,
+        );
+        if let Some((_, r, w)) = merged {
+            data[write] = MfNode { u, v, r };
+            if weighted {
+                weights[write] = w;
+            }
+            write += 1;
+        }
+        read = end;
+    }
+    data.truncate(write);
+    weights.truncate(write);
+}
+
+// splits sorted items into runs with the same key
+pub(crate) fn runs<T, K: PartialEq, F: Fn(&T) -> K>(items: &[T], key: F) -> Vec<&[T]> {
+    let mut runs = Vec::new();
+    let mut rest = items;
+    while let Some(first) = rest.first() {
+        let k = key(first);
+        let len = rest.iter().take_while(|x| key(x) == k).count();
+        let (run, remaining) = rest.split_at(len);
+        runs.push(run);
+        rest = remaining;
+    }
+    runs
+}
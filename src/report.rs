@@ -0,0 +1,63 @@
+use crate::Loss;
+use core::fmt;
+
+/// The metrics relevant to a loss function.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvaluationReport {
+    /// The loss function of the model.
+    pub loss: Loss,
+    /// The number of entries.
+    pub count: usize,
+    /// RMSE (for real-valued MF).
+    pub rmse: Option<f64>,
+    /// MAE (for real-valued MF).
+    pub mae: Option<f64>,
+    /// Generalized KL-divergence (for non-negative real-valued MF).
+    pub gkl: Option<f64>,
+    /// Logarithmic loss (for binary MF).
+    pub logloss: Option<f64>,
+    /// Accuracy (for binary MF).
+    pub accuracy: Option<f64>,
+    /// MPR (for one-class MF).
+    pub mpr: Option<f64>,
+    /// AUC (for one-class MF).
+    pub auc: Option<f64>,
+}
+
+impl EvaluationReport {
+    pub(crate) fn new(loss: Loss, count: usize) -> Self {
+        Self {
+            loss,
+            count,
+            rmse: None,
+            mae: None,
+            gkl: None,
+            logloss: None,
+            accuracy: None,
+            mpr: None,
+            auc: None,
+        }
+    }
+}
+
+impl fmt::Display for EvaluationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "loss: {:?}\ncount: {}", self.loss, self.count)?;
+        let metrics = [
+            ("rmse", self.rmse),
+            ("mae", self.mae),
+            ("gkl", self.gkl),
+            ("logloss", self.logloss),
+            ("accuracy", self.accuracy),
+            ("mpr", self.mpr),
+            ("auc", self.auc),
+        ];
+        for (name, value) in metrics {
+            if let Some(value) = value {
+                write!(f, "\n{}: {:.6}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use core::f32::consts::PI;
+
+/// How the learning rate changes over iterations.
+///
+/// Each schedule starts from the learning rate set with `Params::learning_rate`.
+/// Schedules other than `Constant` require `Solver::RustSgd`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LearningRateSchedule {
+    /// Use the same learning rate for every iteration.
+    Constant,
+    /// Multiply the learning rate by `gamma` every `step_size` iterations.
+    Step {
+        /// The number of iterations between decays.
+        step_size: i32,
+        /// The factor to multiply by.
+        gamma: f32,
+    },
+    /// Multiply the learning rate by `gamma` every iteration.
+    Exponential {
+        /// The factor to multiply by.
+        gamma: f32,
+    },
+    /// Decrease the learning rate to `min_learning_rate` along a cosine curve.
+    Cosine {
+        /// The learning rate at the end of training.
+        min_learning_rate: f32,
+    },
+    /// Increase the learning rate linearly for `warmup` iterations, then decrease it
+    /// to `min_learning_rate` along a cosine curve.
+    WarmupCosine {
+        /// The number of warmup iterations.
+        warmup: i32,
+        /// The learning rate at the end of training.
+        min_learning_rate: f32,
+    },
+}
+
+impl LearningRateSchedule {
+    /// Returns the learning rate for an iteration (starting from zero).
+    pub fn learning_rate(&self, learning_rate: f32, iteration: i32, iterations: i32) -> f32 {
+        match *self {
+            LearningRateSchedule::Constant => learning_rate,
+            LearningRateSchedule::Step { step_size, gamma } => {
+                learning_rate * libm::powf(gamma, (iteration / step_size.max(1)) as f32)
+            }
+            LearningRateSchedule::Exponential { gamma } => {
+                learning_rate * libm::powf(gamma, iteration as f32)
+            }
+            LearningRateSchedule::Cosine { min_learning_rate } => {
+                cosine(learning_rate, min_learning_rate, iteration, iterations)
+            }
+            LearningRateSchedule::WarmupCosine {
+                warmup,
+                min_learning_rate,
+            } => {
+                if iteration < warmup {
+                    learning_rate * (iteration + 1) as f32 / warmup as f32
+                } else {
+                    cosine(
+                        learning_rate,
+                        min_learning_rate,
+                        iteration - warmup,
+                        iterations - warmup,
+                    )
+                }
+            }
+        }
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        let positive = |v: f32| v > 0.0 && v.is_finite();
+        let non_negative = |v: f32| v >= 0.0 && v.is_finite();
+        match *self {
+            LearningRateSchedule::Constant => true,
+            LearningRateSchedule::Step { step_size, gamma } => step_size >= 1 && positive(gamma),
+            LearningRateSchedule::Exponential { gamma } => positive(gamma),
+            LearningRateSchedule::Cosine { min_learning_rate } => non_negative(min_learning_rate),
+            LearningRateSchedule::WarmupCosine {
+                warmup,
+                min_learning_rate,
+            } => warmup >= 0 && non_negative(min_learning_rate),
+        }
+    }
+}
+
+fn cosine(learning_rate: f32, min_learning_rate: f32, iteration: i32, iterations: i32) -> f32 {
+    let progress = iteration as f32 / iterations.max(1) as f32;
+    min_learning_rate
+        + (learning_rate - min_learning_rate) * (1.0 + libm::cosf(PI * progress)) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LearningRateSchedule;
+
+    fn assert_in_delta(expected: f32, actual: f32) {
+        assert!(
+            (expected - actual).abs() < 1e-6,
+            "expected {} to be close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_constant() {
+        let schedule = LearningRateSchedule::Constant;
+        assert_eq!(schedule.learning_rate(0.1, 0, 20), 0.1);
+        assert_eq!(schedule.learning_rate(0.1, 19, 20), 0.1);
+    }
+
+    #[test]
+    fn test_step() {
+        let schedule = LearningRateSchedule::Step {
+            step_size: 5,
+            gamma: 0.5,
+        };
+        assert_eq!(schedule.learning_rate(0.1, 0, 20), 0.1);
+        assert_eq!(schedule.learning_rate(0.1, 4, 20), 0.1);
+        assert_eq!(schedule.learning_rate(0.1, 5, 20), 0.05);
+        assert_eq!(schedule.learning_rate(0.1, 19, 20), 0.0125);
+    }
+
+    #[test]
+    fn test_exponential() {
+        let schedule = LearningRateSchedule::Exponential { gamma: 0.9 };
+        assert_eq!(schedule.learning_rate(0.1, 0, 20), 0.1);
+        assert_in_delta(0.081, schedule.learning_rate(0.1, 2, 20));
+    }
+
+    #[test]
+    fn test_cosine() {
+        let schedule = LearningRateSchedule::Cosine {
+            min_learning_rate: 0.01,
+        };
+        assert_eq!(schedule.learning_rate(0.1, 0, 20), 0.1);
+        assert_in_delta(0.055, schedule.learning_rate(0.1, 10, 20));
+        assert!(schedule.learning_rate(0.1, 19, 20) > 0.01);
+        assert_in_delta(0.01, schedule.learning_rate(0.1, 20, 20));
+    }
+
+    #[test]
+    fn test_warmup_cosine() {
+        let schedule = LearningRateSchedule::WarmupCosine {
+            warmup: 4,
+            min_learning_rate: 0.0,
+        };
+        assert_in_delta(0.025, schedule.learning_rate(0.1, 0, 20));
+        assert_in_delta(0.075, schedule.learning_rate(0.1, 2, 20));
+        assert_eq!(schedule.learning_rate(0.1, 4, 20), 0.1);
+        assert_in_delta(0.05, schedule.learning_rate(0.1, 12, 20));
+
+        // no warmup
+        let schedule = LearningRateSchedule::WarmupCosine {
+            warmup: 0,
+            min_learning_rate: 0.0,
+        };
+        assert_eq!(schedule.learning_rate(0.1, 0, 20), 0.1);
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(LearningRateSchedule::Constant.is_valid());
+        assert!(!LearningRateSchedule::Step {
+            step_size: 0,
+            gamma: 0.5
+        }
+        .is_valid());
+        assert!(!LearningRateSchedule::Exponential { gamma: 0.0 }.is_valid());
+        assert!(!LearningRateSchedule::Exponential { gamma: f32::NAN }.is_valid());
+        assert!(!LearningRateSchedule::Cosine {
+            min_learning_rate: -0.1
+        }
+        .is_valid());
+        assert!(!LearningRateSchedule::WarmupCosine {
+            warmup: -1,
+            min_learning_rate: 0.0
+        }
+        .is_valid());
+    }
+}
@@ -0,0 +1,701 @@
+use crate::bias::{self, weighted_mean, Biases};
+use crate::bindings::MfParameter;
+use crate::divergence::Monitor;
+use crate::{Error, Loss, Matrix, Model, Params};
+use alloc::vec;
+use alloc::vec::Vec;
+
+// fixed so training is deterministic
+pub(crate) const SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// an entry with indices relative to its block
+struct Entry {
+    u: u32,
+    v: u32,
+    r: f32,
+    w: f32,
+}
+
+// the entries in a block and the factors, gradient sums, and biases for its rows and columns
+struct Block<'a> {
+    entries: &'a [Entry],
+    p: &'a mut [f32],
+    p_g: &'a mut [f32],
+    q: &'a mut [f32],
+    q_g: &'a mut [f32],
+    row_bias: &'a mut [f32],
+    column_bias: &'a mut [f32],
+}
+
+// which biases to update and their regularization
+struct BiasParam {
+    rows: bool,
+    columns: bool,
+    lambda: f32,
+}
+
+// stochastic gradient descent with per-entry weights (for real-valued and binary MF)
+//
+// follows the updates in LIBMF: adaptive learning rates for each row and column, and
+// a grid of blocks where blocks that share no rows or columns are updated in parallel
+//
+// `param` can differ from `params` when retrying or training a single iteration, and
+// the schedule uses the iterations from `params`, so training can stop early with the
+// same learning rates
+//
+// row and column biases are updated with the factors, with their own regularization
+pub(crate) fn train(
+    data: &Matrix,
+    eval_set: Option<&Matrix>,
+    param: &MfParameter,
+    params: &Params,
+) -> Result<Model, Error> {
+    let k = param.k as usize;
+    let mut m = data.rows() as usize;
+    let mut n = data.columns() as usize;
+    // LIBMF uses the larger dimensions
+    if let Some(eval_set) = eval_set {
+        m = m.max(eval_set.rows() as usize);
+        n = n.max(eval_set.columns() as usize);
+    }
+
+    let mut rng = Rng(SEED);
+    let scale = libm::sqrtf(1.0 / k as f32);
+    let mut p: Vec<f32> = (0..m * k).map(|_| rng.next_f32() * scale).collect();
+    let mut q: Vec<f32> = (0..n * k).map(|_| rng.next_f32() * scale).collect();
+    let mut p_g = vec![1.0; m];
+    let mut q_g = vec![1.0; n];
+    // split into blocks like the factors, so both sides always have a bias
+    let mut row_bias = vec![0.0; m];
+    let mut column_bias = vec![0.0; n];
+    let bias_param = BiasParam {
+        rows: params.get_row_bias(),
+        columns: params.get_column_bias(),
+        lambda: params.get_lambda_bias(),
+    };
+
+    let bins = param.nr_bins.max(1) as usize;
+    let row_starts = starts(m, bins);
+    let column_starts = starts(n, bins);
+    let blocks = partition(data, &row_starts, &column_starts, &mut rng);
+
+    #[cfg(feature = "std")]
+    let progress = Progress::new(data, eval_set, param);
+    let mut monitor = Monitor::new(params.get_divergence_check());
+    let schedule = params.get_learning_rate_schedule();
+
+    for iter in 0..param.nr_iters {
+        let mut step_param = *param;
+        step_param.eta = schedule.learning_rate(param.eta, iter, params.get_iterations());
+        let mut loss = 0.0;
+        // each step updates blocks on a diagonal of the grid
+        for step in 0..bins {
+            let mut q_blocks: Vec<_> = split(&mut q, &column_starts, k)
+                .into_iter()
+                .zip(split(&mut q_g, &column_starts, 1))
+                .zip(split(&mut column_bias, &column_starts, 1))
+                .map(Some)
+                .collect();
+            let mut tasks = Vec::with_capacity(bins);
+            let p_blocks = split(&mut p, &row_starts, k)
+                .into_iter()
+                .zip(split(&mut p_g, &row_starts, 1))
+                .zip(split(&mut row_bias, &row_starts, 1));
+            for (i, ((p, p_g), row_bias)) in p_blocks.enumerate() {
+                let j = (i + step) % bins;
+                let ((q, q_g), column_bias) = q_blocks[j].take().unwrap();
+                let entries = &blocks[i * bins + j];
+                if !entries.is_empty() {
+                    tasks.push(Block {
+                        entries,
+                        p,
+                        p_g,
+                        q,
+                        q_g,
+                        row_bias,
+                        column_bias,
+                    });
+                }
+            }
+            loss += run(&mut tasks, &step_param, &bias_param);
+        }
+
+        #[cfg(feature = "std")]
+        progress.print(iter, loss, &p, &q, &row_bias, &column_bias);
+        monitor.check(iter, loss)?;
+    }
+
+    fill_unseen(data, k, &mut p, &mut q);
+    let mut model = Model::from_factors(param.fun, param.k, weighted_mean(data), p, q);
+    model.biases = Biases {
+        rows: if bias_param.rows {
+            row_bias
+        } else {
+            Vec::new()
+        },
+        columns: if bias_param.columns {
+            column_bias
+        } else {
+            Vec::new()
+        },
+    };
+    Ok(model)
+}
+
+// like LIBMF, rows and columns without entries have NaN factors
+pub(crate) fn fill_unseen(data: &Matrix, k: usize, p: &mut [f32], q: &mut [f32]) {
+    for (factors, nnz) in [(p, data.nnz_per_row()), (q, data.nnz_per_column())] {
+        for (i, v) in factors.chunks_mut(k).enumerate() {
+            if nnz.get(i).map_or(true, |&c| c == 0) {
+                v.fill(f32::NAN);
+            }
+        }
+    }
+}
+
+// the first row or column in each block, plus the end
+fn starts(len: usize, bins: usize) -> Vec<usize> {
+    (0..=bins).map(|i| (i * len + bins - 1) / bins).collect()
+}
+
+fn partition(
+    data: &Matrix,
+    row_starts: &[usize],
+    column_starts: &[usize],
+    rng: &mut Rng,
+) -> Vec<Vec<Entry>> {
+    let bins = row_starts.len() - 1;
+    let m = row_starts[bins];
+    let n = column_starts[bins];
+
+    // shuffle once so updates are not in the order of the data
+    let mut order: Vec<usize> = (0..data.len()).collect();
+    rng.shuffle(&mut order);
+
+    let mut blocks: Vec<Vec<Entry>> = (0..bins * bins).map(|_| Vec::new()).collect();
+    for i in order {
+        let x = &data.data[i];
+        let (u, v) = (x.u as usize, x.v as usize);
+        let (bi, bj) = (u * bins / m, v * bins / n);
+        blocks[bi * bins + bj].push(Entry {
+            u: (u - row_starts[bi]) as u32,
+            v: (v - column_starts[bj]) as u32,
+            r: x.r,
+            w: data.weight(i),
+        });
+    }
+    blocks
+}
+
+fn split<'a>(mut values: &'a mut [f32], starts: &[usize], width: usize) -> Vec<&'a mut [f32]> {
+    let mut blocks = Vec::with_capacity(starts.len() - 1);
+    for bounds in starts.windows(2) {
+        let (block, rest) =
+            core::mem::take(&mut values).split_at_mut((bounds[1] - bounds[0]) * width);
+        blocks.push(block);
+        values = rest;
+    }
+    blocks
+}
+
+// returns the weighted sum of the loss before each update
+#[cfg(feature = "std")]
+fn run(tasks: &mut [Block], param: &MfParameter, bias_param: &BiasParam) -> f64 {
+    let threads = param.nr_threads.max(1) as usize;
+    if threads < 2 || tasks.len() < 2 {
+        return tasks
+            .iter_mut()
+            .map(|b| update_block(b, param, bias_param))
+            .sum();
+    }
+
+    let chunk_size = (tasks.len() + threads - 1) / threads;
+    std::thread::scope(|s| {
+        let handles: Vec<_> = tasks
+            .chunks_mut(chunk_size)
+            .map(|chunk| {
+                s.spawn(|| {
+                    chunk
+                        .iter_mut()
+                        .map(|b| update_block(b, param, bias_param))
+                        .sum::<f64>()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+}
+
+#[cfg(not(feature = "std"))]
+fn run(tasks: &mut [Block], param: &MfParameter, bias_param: &BiasParam) -> f64 {
+    tasks
+        .iter_mut()
+        .map(|b| update_block(b, param, bias_param))
+        .sum()
+}
+
+fn update_block(block: &mut Block, param: &MfParameter, bias_param: &BiasParam) -> f64 {
+    let k = param.k as usize;
+    let mut loss = 0.0;
+    for x in block.entries {
+        let (u, v) = (x.u as usize, x.v as usize);
+        let p = &mut block.p[u * k..(u + 1) * k];
+        let q = &mut block.q[v * k..(v + 1) * k];
+
+        let z = dot(p, q) + block.row_bias[u] + block.column_bias[v];
+        let (g, l) = gradient(param.fun, x.r, z);
+        loss += x.w as f64 * l as f64;
+        if x.w == 0.0 {
+            continue;
+        }
+
+        if bias_param.rows {
+            bias::step(&mut block.row_bias[u], g, x.w, param.eta, bias_param.lambda);
+        }
+        if bias_param.columns {
+            bias::step(
+                &mut block.column_bias[v],
+                g,
+                x.w,
+                param.eta,
+                bias_param.lambda,
+            );
+        }
+
+        let eta_p = param.eta / libm::sqrtf(block.p_g[u]);
+        let eta_q = param.eta / libm::sqrtf(block.q_g[v]);
+        let mut p_g = 0.0;
+        let mut q_g = 0.0;
+        for d in 0..k {
+            // the weight scales the whole gradient, like repeating the entry
+            let gp = x.w * (-g * q[d] + param.lambda_p2 * p[d]);
+            let gq = x.w * (-g * p[d] + param.lambda_q2 * q[d]);
+            p_g += gp * gp;
+            q_g += gq * gq;
+            p[d] -= eta_p * gp;
+            q[d] -= eta_q * gq;
+        }
+
+        if param.lambda_p1 > 0.0 {
+            soft_threshold(p, x.w * param.lambda_p1 * eta_p);
+        }
+        if param.lambda_q1 > 0.0 {
+            soft_threshold(q, x.w * param.lambda_q1 * eta_q);
+        }
+        if param.do_nmf {
+            p.iter_mut().for_each(|v| *v = v.max(0.0));
+            q.iter_mut().for_each(|v| *v = v.max(0.0));
+        }
+
+        block.p_g[u] += p_g / k as f32;
+        block.q_g[v] += q_g / k as f32;
+    }
+    loss
+}
+
+// same order of operations as LIBMF
+pub(crate) fn dot(p: &[f32], q: &[f32]) -> f32 {
+    p.iter().zip(q).fold(0.0, |acc, (a, b)| acc + a * b)
+}
+
+// returns the negative gradient with respect to the prediction and the loss
+pub(crate) fn gradient(fun: Loss, r: f32, z: f32) -> (f32, f32) {
+    match fun {
+        Loss::RealL2 => {
+            let e = r - z;
+            (e, e * e)
+        }
+        Loss::RealL1 => {
+            let e = r - z;
+            let g = if e > 0.0 {
+                1.0
+            } else if e < 0.0 {
+                -1.0
+            } else {
+                0.0
+            };
+            (g, e.abs())
+        }
+        Loss::RealKL => {
+            let z = z.max(f32::EPSILON);
+            let l = if r > 0.0 { r * libm::logf(r / z) } else { 0.0 } - r + z;
+            (r / z - 1.0, l)
+        }
+        Loss::BinaryLog => {
+            let y = label(r);
+            (
+                y / (1.0 + libm::expf(y * z)),
+                libm::log1pf(libm::expf(-y * z)),
+            )
+        }
+        Loss::BinaryL2 => {
+            let y = label(r);
+            let margin = (1.0 - y * z).max(0.0);
+            (y * margin, margin * margin)
+        }
+        Loss::BinaryL1 => {
+            let y = label(r);
+            let margin = (1.0 - y * z).max(0.0);
+            (if margin > 0.0 { y } else { 0.0 }, margin)
+        }
+        // for entries in the training set (trained with ALS)
+        Loss::OneClassL2 => {
+            let e = r - z;
+            (e, e * e)
+        }
+        Loss::OneClassRow | Loss::OneClassCol => {
+            unreachable!("one-class losses are trained by LIBMF")
+        }
+    }
+}
+
+fn label(r: f32) -> f32 {
+    if r > 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+pub(crate) fn soft_threshold(values: &mut [f32], threshold: f32) {
+    for v in values {
+        let magnitude = (v.abs() - threshold).max(0.0);
+        *v = if *v >= 0.0 { magnitude } else { -magnitude };
+    }
+}
+
+// xorshift64*
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // uniform in [0, 1)
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+// prints the loss after each iteration like LIBMF
+#[cfg(feature = "std")]
+pub(crate) struct Progress<'a> {
+    quiet: bool,
+    fun: Loss,
+    k: usize,
+    total_weight: f64,
+    eval_set: Option<&'a Matrix>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> Progress<'a> {
+    pub(crate) fn new(data: &Matrix, eval_set: Option<&'a Matrix>, param: &MfParameter) -> Self {
+        let progress = Self {
+            quiet: param.quiet,
+            fun: param.fun,
+            k: param.k as usize,
+            total_weight: (0..data.len()).map(|i| data.weight(i) as f64).sum(),
+            eval_set,
+        };
+        if !progress.quiet {
+            if eval_set.is_some() {
+                std::println!("{:>4}{:>13}{:>13}", "iter", "tr_loss", "va_loss");
+            } else {
+                std::println!("{:>4}{:>13}", "iter", "tr_loss");
+            }
+        }
+        progress
+    }
+
+    // the biases are zero when disabled
+    pub(crate) fn print(
+        &self,
+        iter: i32,
+        loss: f64,
+        p: &[f32],
+        q: &[f32],
+        row_bias: &[f32],
+        column_bias: &[f32],
+    ) {
+        if self.quiet {
+            return;
+        }
+        let tr_loss = loss / self.total_weight;
+        match self.eval_set {
+            Some(eval_set) => {
+                let k = self.k;
+                let mut sum = 0.0;
+                for x in &eval_set.data {
+                    let (u, v) = (x.u as usize, x.v as usize);
+                    let z = dot(&p[u * k..(u + 1) * k], &q[v * k..(v + 1) * k])
+                        + bias::get(row_bias, x.u)
+                        + bias::get(column_bias, x.v);
+                    sum += gradient(self.fun, x.r, z).1 as f64;
+                }
+                let va_loss = sum / eval_set.len() as f64;
+                std::println!("{:>4}{:>13.4}{:>13.4}", iter, tr_loss, va_loss);
+            }
+            None => std::println!("{:>4}{:>13.4}", iter, tr_loss),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gradient, split, starts, Rng};
+    use crate::{LearningRateSchedule, Loss, Matrix, Model, ParameterError, Solver};
+
+    #[test]
+    fn test_starts() {
+        assert_eq!(starts(10, 3), [0, 4, 7, 10]);
+        assert_eq!(starts(2, 4), [0, 1, 1, 2, 2]);
+        for len in 0..20 {
+            let starts = starts(len, 5);
+            for u in 0..len {
+                let i = u * 5 / len;
+                assert!(starts[i] <= u && u < starts[i + 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_split() {
+        let mut values = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let blocks = split(&mut values, &[0, 1, 1, 3], 2);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0], [0.0, 1.0]);
+        assert!(blocks[1].is_empty());
+        assert_eq!(blocks[2], [2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn test_gradient() {
+        assert_eq!(gradient(Loss::RealL2, 3.0, 1.0), (2.0, 4.0));
+        assert_eq!(gradient(Loss::RealL1, 1.0, 3.0), (-1.0, 2.0));
+        assert_eq!(gradient(Loss::BinaryL2, -1.0, 0.5), (-1.5, 2.25));
+        assert_eq!(gradient(Loss::BinaryL1, 1.0, 2.0), (0.0, 0.0));
+        let (g, l) = gradient(Loss::BinaryLog, 1.0, 0.0);
+        assert_eq!(g, 0.5);
+        assert!((l - core::f32::consts::LN_2).abs() < 1e-6);
+        let (g, l) = gradient(Loss::RealKL, 2.0, 2.0);
+        assert_eq!((g, l), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_rng() {
+        let mut rng = Rng(1);
+        let mut items = [0, 1, 2, 3, 4, 5, 6, 7];
+        rng.shuffle(&mut items);
+        items.sort_unstable();
+        assert_eq!(items, [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!((0..100)
+            .map(|_| rng.next_f32())
+            .all(|v| (0.0..1.0).contains(&v)));
+    }
+
+    fn generate_data() -> Matrix {
+        let mut data = Matrix::new();
+        for i in 0..20 {
+            for j in 0..10 {
+                if (i + j) % 3 != 0 {
+                    data.push(i, j, ((i % 4) + (j % 3)) as f32);
+                }
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_train() {
+        let data = generate_data();
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .lambda_p2(0.0)
+            .lambda_q2(0.0)
+            .iterations(50)
+            .fit(&data)
+            .unwrap();
+        assert_eq!(model.rows(), 20);
+        assert_eq!(model.columns(), 10);
+        assert!(model.rmse(&data).unwrap() < 0.2);
+    }
+
+    #[test]
+    fn test_train_deterministic() {
+        let data = generate_data();
+        let mut params = Model::params();
+        params.quiet(true).solver(Solver::RustSgd).threads(4);
+        let model = params.fit(&data).unwrap();
+        let model2 = params.fit(&data).unwrap();
+        assert_eq!(model.p_factors(), model2.p_factors());
+        assert_eq!(model.q_factors(), model2.q_factors());
+    }
+
+    #[test]
+    fn test_train_weights() {
+        // conflicting values for the same entry
+        let mut data = Matrix::new();
+        data.push_weighted(0, 0, 1.0, 3.0);
+        data.push_weighted(0, 0, 0.0, 1.0);
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .factors(1)
+            .lambda_p2(0.0)
+            .lambda_q2(0.0)
+            .iterations(200)
+            .fit(&data)
+            .unwrap();
+        assert!((model.predict(0, 0) - 0.75).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_train_unseen() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push(2, 2, 1.0);
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .fit(&data)
+            .unwrap();
+        assert!(model.p(1).unwrap().iter().all(|v| v.is_nan()));
+        assert!(!model.is_known_row(1));
+        assert_eq!(model.predict(1, 0), model.bias());
+    }
+
+    #[test]
+    fn test_train_binary() {
+        let mut data = Matrix::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                let label = if (i < 5) == (j < 5) { 1.0 } else { -1.0 };
+                data.push(i, j, label);
+            }
+        }
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .loss(Loss::BinaryLog)
+            .fit(&data)
+            .unwrap();
+        assert_eq!(model.accuracy(&data).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_fit_eval() {
+        let data = generate_data();
+        let mut eval_set = Matrix::new();
+        eval_set.push(25, 0, 1.0);
+        let model = Model::params()
+            .quiet(true)
+            .solver(Solver::RustSgd)
+            .fit_eval(&data, &eval_set)
+            .unwrap();
+        assert_eq!(model.rows(), 26);
+        assert!(!model.is_known_row(25));
+    }
+
+    #[test]
+    fn test_unit_weights() {
+        let data = generate_data();
+        let mut weighted = Matrix::new();
+        for x in &data.data {
+            weighted.push_weighted(x.u, x.v, x.r, 1.0);
+        }
+        let mut params = Model::params();
+        params.quiet(true).solver(Solver::RustSgd);
+        let model = params.fit(&data).unwrap();
+        let weighted_model = params.fit(&weighted).unwrap();
+        assert_eq!(model.p_factors(), weighted_model.p_factors());
+        assert_eq!(model.q_factors(), weighted_model.q_factors());
+    }
+
+    #[test]
+    fn test_weight_errors() {
+        let mut data = Matrix::new();
+        data.push(0, 0, 1.0);
+        data.push_weighted(1, 1, 1.0, 2.0);
+        let result = Model::params().fit(&data);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::WeightSolver(Solver::Sgd).into()
+        );
+
+        let mut params = Model::params();
+        params.solver(Solver::RustSgd);
+        let result = params.clone().loss(Loss::OneClassRow).fit(&data);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::SolverLoss(Solver::RustSgd, Loss::OneClassRow).into()
+        );
+
+        data.push_weighted(2, 2, 1.0, -1.0);
+        let result = params.fit(&data);
+        assert_eq!(result.unwrap_err(), ParameterError::Weight(2).into());
+
+        let result = Model::params().cv(&data, 2);
+        assert_eq!(result.unwrap_err(), ParameterError::WeightCv.into());
+
+        let result = params.cv(&generate_data(), 2);
+        assert_eq!(
+            result.unwrap_err(),
+            ParameterError::SolverCv(Solver::RustSgd).into()
+        );
+    }
+
+    #[test]
+    fn test_train_schedule() {
+        let data = generate_data();
+        let fit = |schedule| {
+            Model::params()
+                .quiet(true)
+                .solver(Solver::RustSgd)
+                .learning_rate_schedule(schedule)
+                .fit(&data)
+                .unwrap()
+        };
+
+        // a gamma of 1 keeps the learning rate constant
+        let constant = fit(LearningRateSchedule::Constant);
+        let step = fit(LearningRateSchedule::Step {
+            step_size: 5,
+            gamma: 1.0,
+        });
+        assert_eq!(step.p_factors(), constant.p_factors());
+        assert_eq!(step.q_factors(), constant.q_factors());
+        let exponential = fit(LearningRateSchedule::Exponential { gamma: 1.0 });
+        assert_eq!(exponential.p_factors(), constant.p_factors());
+
+        let cosine = fit(LearningRateSchedule::Cosine {
+            min_learning_rate: 0.0,
+        });
+        assert_ne!(step.p_factors(), cosine.p_factors());
+        assert!(cosine.evaluator().unwrap().rmse(&data).unwrap() < 0.5);
+        assert_eq!(
+            cosine
+                .metadata()
+                .params
+                .as_ref()
+                .unwrap()
+                .get_learning_rate_schedule(),
+            LearningRateSchedule::Cosine {
+                min_learning_rate: 0.0
+            }
+        );
+    }
+}
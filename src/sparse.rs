@@ -0,0 +1,138 @@
+use crate::bindings::MfNode;
+use crate::{Error, SparseError};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::c_int;
+
+/// A matrix in compressed sparse row (CSR) or column (CSC) format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompressedMatrix {
+    /// The offset in `indices` and `data` where each row (or column) starts, followed by the number of entries.
+    pub indptr: Vec<usize>,
+    /// The column (or row) index of each entry.
+    pub indices: Vec<usize>,
+    /// The value of each entry.
+    pub data: Vec<f32>,
+}
+
+pub(crate) fn from_compressed(
+    indptr: &[usize],
+    indices: &[usize],
+    data: &[f32],
+    transpose: bool,
+) -> Result<Vec<MfNode>, Error> {
+    if indices.len() != data.len() {
+        return Err(SparseError::Length {
+            indices: indices.len(),
+            data: data.len(),
+        }
+        .into());
+    }
+    if indptr.first() != Some(&0) {
+        return Err(SparseError::IndptrStart.into());
+    }
+    if indptr.last() != Some(&data.len()) {
+        return Err(SparseError::IndptrEnd.into());
+    }
+    if let Some(i) = indptr.windows(2).position(|w| w[0] > w[1]) {
+        return Err(SparseError::IndptrOrder(i + 1).into());
+    }
+    if indptr.len() - 1 > c_int::MAX as usize {
+        return Err(SparseError::TooManyRowsOrColumns(indptr.len() - 1).into());
+    }
+
+    let mut nodes = Vec::with_capacity(data.len());
+    for (outer, w) in indptr.windows(2).enumerate() {
+        for k in w[0]..w[1] {
+            let inner = c_int::try_from(indices[k])
+                .map_err(|_| SparseError::IndexOutOfRange(indices[k]))?;
+            let (u, v) = if transpose {
+                (inner, outer as c_int)
+            } else {
+                (outer as c_int, inner)
+            };
+            nodes.push(MfNode { u, v, r: data[k] });
+        }
+    }
+    Ok(nodes)
+}
+
+// expects valid indices
+pub(crate) fn to_compressed(
+    data: &[MfNode],
+    outer_len: usize,
+    transpose: bool,
+) -> CompressedMatrix {
+    let split = |x: &MfNode| {
+        if transpose {
+            (x.v as usize, x.u as usize)
+        } else {
+            (x.u as usize, x.v as usize)
+        }
+    };
+
+    // counting sort by outer index, then stable sort within each
+    let mut indptr = vec![0; outer_len + 1];
+    for x in data {
+        indptr[split(x).0 + 1] += 1;
+    }
+    for i in 0..outer_len {
+        indptr[i + 1] += indptr[i];
+    }
+
+    let mut next = indptr.clone();
+    let mut order = vec![0; data.len()];
+    for (i, x) in data.iter().enumerate() {
+        let outer = split(x).0;
+        order[next[outer]] = i;
+        next[outer] += 1;
+    }
+    for w in indptr.windows(2) {
+        order[w[0]..w[1]].sort_by_key(|&i| split(&data[i]).1);
+    }
+
+    CompressedMatrix {
+        indptr,
+        indices: order.iter().map(|&i| split(&data[i]).1).collect(),
+        data: order.iter().map(|&i| data[i].r).collect(),
+    }
+}
+
+#[cfg(feature = "sprs")]
+mod interop {
+    use crate::bindings::MfProblem;
+    use crate::{Error, Matrix, SparseError};
+    use core::ffi::c_int;
+    use sprs::{CsMat, TriMat};
+
+    impl TryFrom<&CsMat<f32>> for Matrix {
+        type Error = Error;
+
+        fn try_from(mat: &CsMat<f32>) -> Result<Self, Self::Error> {
+            let mut matrix = Matrix::with_capacity(mat.nnz());
+            for (&value, (i, j)) in mat.iter() {
+                let i = c_int::try_from(i).map_err(|_| SparseError::IndexOutOfRange(i))?;
+                let j = c_int::try_from(j).map_err(|_| SparseError::IndexOutOfRange(j))?;
+                matrix.push(i, j, value);
+            }
+            Ok(matrix)
+        }
+    }
+
+    impl Matrix {
+        /// Converts the matrix to a `sprs` CSR matrix.
+        ///
+        /// Values of entries with the same row and column are summed.
+        pub fn to_sprs(&self) -> Result<CsMat<f32>, Error> {
+            // check for invalid nodes
+            let _: MfProblem = self.try_into()?;
+
+            let mut mat =
+                TriMat::with_capacity((self.rows() as usize, self.columns() as usize), self.len());
+            for (row_index, column_index, value) in self {
+                mat.add_triplet(row_index as usize, column_index as usize, value);
+            }
+            Ok(mat.to_csr())
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Diagnostics for a model.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModelSummary {
+    /// Diagnostics for the latent factors for rows (P).
+    pub p: FactorSummary,
+    /// Diagnostics for the latent factors for columns (Q).
+    pub q: FactorSummary,
+}
+
+/// Diagnostics for the latent factors for one side of a model.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FactorSummary {
+    /// The number of rows or columns.
+    pub count: usize,
+    /// The number with all factors equal to zero.
+    pub zero: usize,
+    /// The number with a NaN or infinite factor.
+    pub non_finite: usize,
+    /// The fraction that did not appear in training (LIBMF sets all of their factors to NaN).
+    pub unseen_fraction: f64,
+    /// The smallest L2 norm of finite factors.
+    pub min_norm: f64,
+    /// The median L2 norm of finite factors.
+    pub median_norm: f64,
+    /// The 90th percentile L2 norm of finite factors.
+    pub p90_norm: f64,
+    /// The largest L2 norm of finite factors.
+    pub max_norm: f64,
+    /// The mean L2 norm of finite factors.
+    pub mean_norm: f64,
+    /// The singular values of the finite factors, in descending order.
+    pub singular_values: Vec<f64>,
+    /// The effective rank (exponential of the entropy of the normalized singular values).
+    pub effective_rank: f64,
+}
+
+impl FactorSummary {
+    pub(crate) fn new(factors: &[f32], k: usize) -> Self {
+        let count = factors.len().checked_div(k).unwrap_or(0);
+        let mut summary = FactorSummary {
+            count,
+            zero: 0,
+            non_finite: 0,
+            unseen_fraction: 0.0,
+            min_norm: 0.0,
+            median_norm: 0.0,
+            p90_norm: 0.0,
+            max_norm: 0.0,
+            mean_norm: 0.0,
+            singular_values: Vec::new(),
+            effective_rank: 0.0,
+        };
+        if count == 0 {
+            return summary;
+        }
+
+        let mut unseen = 0;
+        let mut norms = Vec::with_capacity(count);
+        let mut gram = vec![0.0; k * k];
+        for row in factors.chunks(k) {
+            if row.iter().all(|v| v.is_nan()) {
+                unseen += 1;
+            }
+            if !row.iter().all(|v| v.is_finite()) {
+                summary.non_finite += 1;
+                continue;
+            }
+            if row.iter().all(|&v| v == 0.0) {
+                summary.zero += 1;
+            }
+
+            norms.push(libm::sqrt(row.iter().map(|&v| v as f64 * v as f64).sum()));
+            for i in 0..k {
+                for j in i..k {
+                    gram[i * k + j] += row[i] as f64 * row[j] as f64;
+                }
+            }
+        }
+        summary.unseen_fraction = unseen as f64 / count as f64;
+
+        if !norms.is_empty() {
+            norms.sort_unstable_by(|a, b| a.total_cmp(b));
+            summary.min_norm = norms[0];
+            summary.median_norm = percentile(&norms, 0.5);
+            summary.p90_norm = percentile(&norms, 0.9);
+            summary.max_norm = norms[norms.len() - 1];
+            summary.mean_norm = norms.iter().sum::<f64>() / norms.len() as f64;
+
+            for i in 0..k {
+                for j in 0..i {
+                    gram[i * k + j] = gram[j * k + i];
+                }
+            }
+            // singular values of the factors are the square roots of the eigenvalues of the Gram matrix
+            let mut singular_values: Vec<f64> = eigenvalues(&mut gram, k)
+                .into_iter()
+                .map(|v| libm::sqrt(v.max(0.0)))
+                .collect();
+            singular_values.sort_unstable_by(|a, b| b.total_cmp(a));
+            summary.effective_rank = effective_rank(&singular_values);
+            summary.singular_values = singular_values;
+        }
+
+        summary
+    }
+}
+
+// nearest-rank method on sorted values
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = libm::ceil(q * sorted.len() as f64) as usize;
+    sorted[rank.max(1) - 1]
+}
+
+fn effective_rank(singular_values: &[f64]) -> f64 {
+    let total: f64 = singular_values.iter().sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    let entropy: f64 = singular_values
+        .iter()
+        .map(|&s| s / total)
+        .filter(|&p| p > 0.0)
+        .map(|p| -p * libm::log(p))
+        .sum();
+    libm::exp(entropy)
+}
+
+// cyclic Jacobi method for a symmetric matrix (which is modified in place)
+fn eigenvalues(a: &mut [f64], n: usize) -> Vec<f64> {
+    for _ in 0..100 {
+        let mut off = 0.0;
+        for i in 0..n {
+            for j in (i + 1)..n {
+                off += a[i * n + j] * a[i * n + j];
+            }
+        }
+        let scale: f64 = (0..n).map(|i| a[i * n + i].abs()).sum();
+        if off <= 1e-30 * (scale * scale).max(f64::MIN_POSITIVE) {
+            break;
+        }
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + libm::sqrt(theta * theta + 1.0));
+                let c = 1.0 / libm::sqrt(t * t + 1.0);
+                let s = t * c;
+                for r in 0..n {
+                    let arp = a[r * n + p];
+                    let arq = a[r * n + q];
+                    a[r * n + p] = c * arp - s * arq;
+                    a[r * n + q] = s * arp + c * arq;
+                }
+                for r in 0..n {
+                    let apr = a[p * n + r];
+                    let aqr = a[q * n + r];
+                    a[p * n + r] = c * apr - s * aqr;
+                    a[q * n + r] = s * apr + c * aqr;
+                }
+            }
+        }
+    }
+    (0..n).map(|i| a[i * n + i]).collect()
+}
+
+impl fmt::Display for ModelSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, side) in [("p", &self.p), ("q", &self.q)] {
+            if name == "q" {
+                writeln!(f)?;
+            }
+            writeln!(
+                f,
+                "{}: count: {}, zero: {}, non-finite: {}, unseen: {:.6}",
+                name, side.count, side.zero, side.non_finite, side.unseen_fraction
+            )?;
+            writeln!(
+                f,
+                "{}: norm min: {:.6}, median: {:.6}, p90: {:.6}, max: {:.6}, mean: {:.6}",
+                name, side.min_norm, side.median_norm, side.p90_norm, side.max_norm, side.mean_norm
+            )?;
+            write!(f, "{}: effective rank: {:.6}", name, side.effective_rank)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{eigenvalues, FactorSummary};
+
+    fn assert_in_delta(expected: f64, actual: f64) {
+        assert!(
+            (expected - actual).abs() < 1e-6,
+            "expected {} to be close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_factor_summary() {
+        let factors = [3.0, 4.0, 0.0, 0.0, f32::NAN, f32::NAN, 1.0, 0.0];
+        let summary = FactorSummary::new(&factors, 2);
+        assert_eq!(summary.count, 4);
+        assert_eq!(summary.zero, 1);
+        assert_eq!(summary.non_finite, 1);
+        assert_eq!(summary.unseen_fraction, 0.25);
+        assert_eq!(summary.min_norm, 0.0);
+        assert_eq!(summary.median_norm, 1.0);
+        assert_eq!(summary.p90_norm, 5.0);
+        assert_eq!(summary.max_norm, 5.0);
+        assert_eq!(summary.mean_norm, 2.0);
+
+        // Gram matrix is [[10, 12], [12, 16]]
+        let s = &summary.singular_values;
+        assert_eq!(s.len(), 2);
+        assert!(s[0] > s[1]);
+        assert_in_delta(26.0, s[0] * s[0] + s[1] * s[1]);
+        assert_in_delta(16.0, s[0] * s[0] * s[1] * s[1]);
+        assert!(summary.effective_rank > 1.0 && summary.effective_rank < 2.0);
+    }
+
+    #[test]
+    fn test_factor_summary_rank() {
+        // orthogonal rows with equal norms have full effective rank
+        let factors = [2.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0];
+        let summary = FactorSummary::new(&factors, 3);
+        assert_eq!(summary.singular_values, [2.0, 2.0, 2.0]);
+        assert_in_delta(3.0, summary.effective_rank);
+
+        // identical rows have rank one
+        let factors = [1.0, 2.0, 1.0, 2.0];
+        let summary = FactorSummary::new(&factors, 2);
+        assert_in_delta(1.0, summary.effective_rank);
+    }
+
+    #[test]
+    fn test_factor_summary_empty() {
+        let summary = FactorSummary::new(&[], 2);
+        assert_eq!(summary.count, 0);
+        assert_eq!(summary.unseen_fraction, 0.0);
+        assert!(summary.singular_values.is_empty());
+
+        let summary = FactorSummary::new(&[f32::NAN, f32::NAN], 2);
+        assert_eq!(summary.unseen_fraction, 1.0);
+        assert_eq!(summary.max_norm, 0.0);
+        assert_eq!(summary.effective_rank, 0.0);
+    }
+
+    #[test]
+    fn test_eigenvalues() {
+        let mut a = [4.0, 1.0, 2.0, 1.0, 3.0, 0.0, 2.0, 0.0, 5.0];
+        let mut values = eigenvalues(&mut a, 3);
+        values.sort_unstable_by(|a, b| a.total_cmp(b));
+        // trace and determinant are preserved
+        assert_in_delta(12.0, values.iter().sum());
+        assert_in_delta(43.0, values.iter().product());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::bindings::MfNode;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Which side of a matrix to treat as documents when weighting.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Axis {
+    /// Each row is a document and each column is a term.
+    Rows,
+    /// Each column is a document and each row is a term.
+    Columns,
+}
+
+fn split(x: &MfNode, axis: Axis) -> (usize, usize) {
+    match axis {
+        Axis::Rows => (x.u as usize, x.v as usize),
+        Axis::Columns => (x.v as usize, x.u as usize),
+    }
+}
+
+// smoothed inverse document frequency for each term, which is always positive
+// (unlike the implicit library, which gives zero or negative weights to common terms)
+fn idf(data: &[MfNode], axis: Axis) -> Vec<f64> {
+    let mut documents = 0;
+    let mut frequencies = Vec::new();
+    for x in data {
+        let (document, term) = split(x, axis);
+        documents = documents.max(document + 1);
+        if term >= frequencies.len() {
+            frequencies.resize(term + 1, 0);
+        }
+        frequencies[term] += 1;
+    }
+
+    let n = documents as f64;
+    frequencies
+        .iter()
+        .map(|&f| 1.0 + libm::log(n / f.max(1) as f64))
+        .collect()
+}
+
+pub(crate) fn tfidf(data: &[MfNode], axis: Axis) -> Vec<MfNode> {
+    let idf = idf(data, axis);
+    data.iter()
+        .map(|x| {
+            let (_, term) = split(x, axis);
+            let r = libm::sqrt(x.r as f64) * idf[term];
+            MfNode {
+                u: x.u,
+                v: x.v,
+                r: r as f32,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn bm25(data: &[MfNode], axis: Axis, k1: f32, b: f32) -> Vec<MfNode> {
+    let idf = idf(data, axis);
+
+    let mut lengths = Vec::new();
+    for x in data {
+        let (document, _) = split(x, axis);
+        if document >= lengths.len() {
+            lengths.resize(document + 1, 0.0);
+        }
+        lengths[document] += x.r as f64;
+    }
+    let average_length = if lengths.is_empty() {
+        0.0
+    } else {
+        lengths.iter().sum::<f64>() / lengths.len() as f64
+    };
+
+    let (k1, b) = (k1 as f64, b as f64);
+    let mut length_norm = vec![0.0; lengths.len()];
+    for (norm, length) in length_norm.iter_mut().zip(&lengths) {
+        // documents are all empty, so there is nothing to normalize
+        *norm = if average_length == 0.0 {
+            1.0
+        } else {
+            (1.0 - b) + b * length / average_length
+        };
+    }
+
+    data.iter()
+        .map(|x| {
+            let (document, term) = split(x, axis);
+            let r = x.r as f64;
+            let denominator = k1 * length_norm[document] + r;
+            let r = if denominator == 0.0 {
+                0.0
+            } else {
+                r * (k1 + 1.0) / denominator * idf[term]
+            };
+            MfNode {
+                u: x.u,
+                v: x.v,
+                r: r as f32,
+            }
+        })
+        .collect()
+}